@@ -0,0 +1,81 @@
+use std::path::{Path, PathBuf};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use symlinkccc::{config::Config, PackageContainer, Source};
+
+const PACKAGES: usize = 3000;
+const PACKAGES_PER_GROUP: usize = 50;
+
+fn write(path: &Path, contents: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, contents).unwrap();
+}
+
+/// Lay out a source space with `PACKAGES` packages, grouped into metapackage-like folders, some
+/// of which carry a vendored tree the search should not enter.
+fn synthetic_source_space() -> PathBuf {
+    let source_path = std::env::temp_dir().join(format!("symlinkccc-bench-{}", std::process::id()));
+
+    for index in 0..PACKAGES {
+        let name = format!("package_{index}");
+        let package_path = source_path
+            .join(format!("group_{}", index / PACKAGES_PER_GROUP))
+            .join(&name);
+
+        write(
+            &package_path.join("package.xml"),
+            &format!(
+                "<?xml version=\"1.0\"?>\n<package format=\"3\">\n  <name>{name}</name>\n  \
+                 <version>0.1.0</version>\n  <buildtool_depend>catkin</buildtool_depend>\n\
+                 </package>\n"
+            ),
+        );
+        write(
+            &package_path.join("CMakeLists.txt"),
+            &format!(
+                "cmake_minimum_required(VERSION 3.10)\n# ${{PROJECT_NAME}}\nproject({name})\n\
+                 find_package(catkin REQUIRED)\ncatkin_package()\n"
+            ),
+        );
+        write(&package_path.join("include").join(&name).join("a.h"), "");
+        write(&package_path.join("src").join("a.cpp"), "");
+        if index % 10 == 0 {
+            write(
+                &package_path
+                    .join("third_party")
+                    .join("lib")
+                    .join("CMakeLists.txt"),
+                "project(lib)\n",
+            );
+        }
+    }
+
+    source_path
+}
+
+fn discovery(c: &mut Criterion) {
+    let source_path = synthetic_source_space();
+    let source = Source::from(&source_path);
+    let config = Config::default();
+    assert_eq!(source.get_all_package_paths(&config).len(), PACKAGES);
+
+    let mut group = c.benchmark_group("discovery");
+    group.sample_size(10);
+
+    let single_thread = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    group.bench_function("single thread", |b| {
+        b.iter(|| single_thread.install(|| source.get_all_package_paths(&config)));
+    });
+    group.bench_function("all threads", |b| {
+        b.iter(|| source.get_all_package_paths(&config));
+    });
+
+    group.finish();
+    std::fs::remove_dir_all(source_path).unwrap();
+}
+
+criterion_group!(benches, discovery);
+criterion_main!(benches);
@@ -0,0 +1,196 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    manifest::{BuildType, Conditions},
+    package_name::PackageName,
+    paths::{
+        package_container::PackageContainer,
+        structs::{Source, SourcePackage, Workspace},
+    },
+    workspace_kind::WorkspaceKind,
+};
+
+/// Bumped whenever the layout of the cache changes, so that old caches are discarded.
+const CACHE_VERSION: u32 = 5;
+
+/// The modification time and size of a file or folder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Stamp {
+    pub modified: SystemTime,
+    pub size: u64,
+}
+
+impl Stamp {
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok()?,
+            size: metadata.len(),
+        })
+    }
+}
+
+/// A folder in the source space, as listed when its stamp was taken.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedFolder {
+    pub stamp: Stamp,
+    /// The ignore marker in the folder, if any.
+    pub ignore_marker: Option<String>,
+    /// Whether the folder contains a `package.xml`, making it a package candidate.
+    pub package_xml: bool,
+    /// The visible subfolders, and symlinks which may point to folders, sorted by name.
+    pub children: Vec<CachedChild>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedChild {
+    pub name: String,
+    pub symlink: bool,
+}
+
+/// A package candidate, as parsed when its stamps were taken.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CachedPackage {
+    pub package_xml: Option<Stamp>,
+    pub cmakelists: Option<Stamp>,
+    /// A `setup.py` makes packages without a declared build type Python packages.
+    pub setup_py: Option<Stamp>,
+    /// The package name, or none if the candidate is not a valid package.
+    pub package_name: Option<PackageName>,
+    pub build_type: Option<BuildType>,
+}
+
+/// What the previous search for packages found, keyed by path.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DiscoveryCache {
+    pub version: u32,
+    pub source_path: PathBuf,
+    /// The condition variables the manifests were evaluated against.
+    pub conditions: Conditions,
+    pub folders: BTreeMap<PathBuf, CachedFolder>,
+    pub packages: BTreeMap<PathBuf, CachedPackage>,
+}
+
+impl DiscoveryCache {
+    /// Read the cache at `path` for the source space at `source_path`, or an empty cache if it is
+    /// missing, outdated, belongs to another source space or was made under other conditions.
+    pub fn read(path: &Path, source_path: &Path) -> Self {
+        let conditions = Conditions::from_env();
+        let cache = std::fs::read(path)
+            .ok()
+            .and_then(|data| serde_json::from_slice::<Self>(&data).ok())
+            .filter(|cache| {
+                cache.version == CACHE_VERSION
+                    && cache.source_path == source_path
+                    && cache.conditions == conditions
+            });
+
+        if let Some(cache) = cache {
+            log::debug!("Using discovery cache {}", path.display());
+            cache
+        } else {
+            log::debug!("No usable discovery cache at {}", path.display());
+            Self::empty(source_path)
+        }
+    }
+
+    pub fn empty(source_path: &Path) -> Self {
+        Self {
+            version: CACHE_VERSION,
+            source_path: source_path.to_path_buf(),
+            conditions: Conditions::from_env(),
+            ..Self::default()
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(self)?)?;
+        std::fs::rename(tmp_path, path)
+    }
+}
+
+/// Where the discovery cache of `workspace` is stored: in the metadata folder of catkin_tools, or
+/// a hidden folder in the workspace root otherwise.
+pub fn cache_path(workspace: &Workspace) -> PathBuf {
+    let cache_dir = match workspace.kind {
+        WorkspaceKind::CatkinTools => workspace
+            .path
+            .join(&workspace.config.metadata_dir_name)
+            .join(crate::config::CACHE_DIR_NAME),
+        WorkspaceKind::Colcon
+        | WorkspaceKind::CatkinMakeIsolated
+        | WorkspaceKind::CatkinMake
+        | WorkspaceKind::Custom => workspace.path.join(crate::config::WORKSPACE_CACHE_DIR_NAME),
+    };
+    cache_dir.join(crate::config::CACHE_FILE_NAME)
+}
+
+/// The source packages of `workspace`, reusing and updating the discovery cache unless caching is
+/// disabled. A `dry_run` only reads the cache, without writing what it found.
+pub fn source_packages(
+    workspace: &Workspace,
+    dry_run: bool,
+) -> BTreeMap<PackageName, SourcePackage> {
+    let source = Source::from(workspace);
+    if !workspace.config.cache {
+        return source.get_all_package_paths(&workspace.config);
+    }
+
+    let path = cache_path(workspace);
+    let Source(source_path) = &source;
+    let mut cache = DiscoveryCache::read(&path, source_path);
+    let source_packages = source.get_all_package_paths_cached(&workspace.config, &mut cache);
+    if dry_run {
+        log::debug!(
+            "Not writing discovery cache {} in a dry run",
+            path.display()
+        );
+    } else if let Err(err) = cache.write(&path) {
+        log::warn!("Cannot write discovery cache {}: {err}", path.display());
+    }
+    source_packages
+}
+
+/// The outcome of clearing the discovery cache.
+#[derive(Debug, Serialize)]
+pub struct Cleared {
+    pub path: PathBuf,
+    /// Whether there was a cache to remove.
+    pub removed: bool,
+}
+
+impl std::fmt::Display for Cleared {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.removed {
+            write!(f, "removed {}", self.path.display())
+        } else {
+            write!(f, "no cache to remove")
+        }
+    }
+}
+
+/// Remove the discovery cache of `workspace`.
+pub fn clear(workspace: &Workspace) -> std::io::Result<Cleared> {
+    let path = cache_path(workspace);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(Cleared {
+            path,
+            removed: true,
+        }),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Cleared {
+            path,
+            removed: false,
+        }),
+        Err(err) => Err(err),
+    }
+}
@@ -0,0 +1,84 @@
+use core::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub enum Error {
+    IO(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(value: serde_json::Error) -> Self {
+        Self::Json(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IO(err) => write!(f, "IO error: {err}"),
+            Self::Json(err) => write!(f, "Invalid compile commands database: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A single entry of a JSON compilation database, as specified by
+/// <https://clang.llvm.org/docs/JSONCompilationDatabase.html>.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CompileCommand {
+    pub directory: PathBuf,
+    pub file: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+}
+
+impl CompileCommand {
+    /// The absolute path of the compiled file, resolving it against `directory` if needed.
+    pub fn absolute_file(&self) -> PathBuf {
+        self.directory.join(&self.file)
+    }
+}
+
+pub fn read<P: AsRef<Path>>(path: P) -> Result<Vec<CompileCommand>> {
+    let path = path.as_ref();
+    log::debug!("Reading compile commands from {}", path.display());
+
+    let data = std::fs::read(path)?;
+    Ok(serde_json::from_slice(&data)?)
+}
+
+/// Write `commands` to `path` by writing a sibling temporary file and renaming it into place,
+/// so that a language server never observes a half-written database.
+pub fn write<P: AsRef<Path>>(path: P, commands: &[CompileCommand]) -> Result<()> {
+    let path = path.as_ref();
+    log::debug!(
+        "Writing {} compile commands to {}",
+        commands.len(),
+        path.display()
+    );
+
+    let mut temporary_name = path.file_name().unwrap_or_default().to_os_string();
+    temporary_name.push(".tmp");
+    let temporary_path = path.with_file_name(temporary_name);
+
+    std::fs::write(&temporary_path, serde_json::to_vec_pretty(commands)?)?;
+    std::fs::rename(&temporary_path, path)?;
+
+    Ok(())
+}
@@ -1 +1,304 @@
-include!(concat!(env!("OUT_DIR"), "/const_config.rs"));
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{copy::Rewrite, output::Format, symlink::LinkMode};
+
+pub const PROFILES_DIR_NAME: &str = "profiles";
+pub const PROFILES_FILE_NAME: &str = "profiles.yaml";
+pub const PROFILE_CONFIG_FILE_NAME: &str = "config.yaml";
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+pub const DEFAULT_BUILD_SPACE: &str = "build";
+pub const DEFAULT_SOURCE_SPACE: &str = "src";
+pub const CATKIN_MAKE_MARKER_NAME: &str = ".catkin_workspace";
+pub const CMAKE_CACHE_NAME: &str = "CMakeCache.txt";
+pub const ISOLATED_BUILD_SPACE: &str = "build_isolated";
+pub const SPLIT_COMPILE_COMMANDS_DIR_NAME: &str = "symlinkccc";
+pub const BACKUP_SUFFIX: &str = ".orig";
+pub const COPY_MARKER_NAME: &str = ".compile_commands.symlinkccc";
+pub const COLCON_IGNORE_NAME: &str = "COLCON_IGNORE";
+pub const COLCON_SPACE_DIRS: [&str; 3] = ["build", "install", "log"];
+/// Marker files which exclude the folder containing them, and everything below it, from builds.
+pub const IGNORE_MARKER_NAMES: [&str; 3] = ["CATKIN_IGNORE", COLCON_IGNORE_NAME, "AMENT_IGNORE"];
+
+/// The folder of the discovery cache in the catkin_tools metadata folder.
+pub const CACHE_DIR_NAME: &str = "symlinkccc";
+/// The folder of the discovery cache in the root of workspaces without a metadata folder.
+pub const WORKSPACE_CACHE_DIR_NAME: &str = ".symlinkccc";
+pub const CACHE_FILE_NAME: &str = "discovery.json";
+
+/// The name of the configuration file in the user configuration directory.
+pub const USER_CONFIG_PATH: &str = "symlinkccc/config.toml";
+/// The name of the configuration file found in the workspace, or any folder above it.
+pub const WORKSPACE_CONFIG_NAME: &str = ".symlinkccc.toml";
+
+#[derive(Debug)]
+pub enum Error {
+    IO(PathBuf, std::io::Error),
+    Toml(PathBuf, toml::de::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IO(path, err) => write!(f, "Cannot read {}: {err}", path.display()),
+            Self::Toml(path, err) => {
+                write!(f, "Invalid configuration in {}: {err}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The effective configuration of symlinkccc.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Config {
+    /// The metadata folder marking the root of a catkin_tools workspace.
+    pub metadata_dir_name: String,
+    /// Folders in the build space which are not packages.
+    pub build_ignore_dirs: Vec<String>,
+    /// The file name of compile commands databases.
+    pub compile_commands_name: String,
+    /// How to link compile commands into source packages, unless given on the command line.
+    pub link_mode: LinkMode,
+    /// Path prefixes to rewrite in copied compile commands, unless given on the command line.
+    pub rewrites: Vec<Rewrite>,
+    /// Glob patterns, relative to the source space, of folders to skip when looking for packages.
+    pub ignore: Vec<String>,
+    /// How to print results, unless given on the command line.
+    pub format: Format,
+    /// Whether to reuse the packages found by the previous run, as far as they did not change.
+    pub cache: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            metadata_dir_name: ".catkin_tools".to_string(),
+            build_ignore_dirs: vec!["build".to_string(), "catkin_tools_prebuild".to_string()],
+            compile_commands_name: "compile_commands.json".to_string(),
+            link_mode: LinkMode::default(),
+            rewrites: vec![],
+            ignore: vec![],
+            format: Format::default(),
+            cache: true,
+        }
+    }
+}
+
+/// A partial configuration, as read from a single configuration file or the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Layer {
+    pub metadata_dir_name: Option<String>,
+    pub build_ignore_dirs: Option<Vec<String>>,
+    pub compile_commands_name: Option<String>,
+    pub link_mode: Option<LinkMode>,
+    pub rewrites: Option<Vec<Rewrite>>,
+    pub ignore: Option<Vec<String>>,
+    pub format: Option<Format>,
+    pub cache: Option<bool>,
+}
+
+impl Layer {
+    /// Read the layer at `path`, or an empty layer if there is no such file.
+    pub fn read(path: &Path) -> Result<Self> {
+        if !path.is_file() {
+            log::debug!("No configuration at {}", path.display());
+            return Ok(Self::default());
+        }
+
+        log::debug!("Reading configuration from {}", path.display());
+        let doc =
+            std::fs::read_to_string(path).map_err(|err| Error::IO(path.to_path_buf(), err))?;
+        toml::from_str(&doc).map_err(|err| Error::Toml(path.to_path_buf(), err))
+    }
+}
+
+/// Where a configuration value came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Default,
+    File(PathBuf),
+    CommandLine,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+impl Serialize for Origin {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A configuration merged from several layers, remembering which layer set each value.
+#[derive(Clone, Debug, Default)]
+pub struct LayeredConfig {
+    pub config: Config,
+    pub origins: BTreeMap<&'static str, Origin>,
+}
+
+macro_rules! apply_layer {
+    ( $self:ident, $layer:ident, $origin:ident, $( $field:ident ), * ) => {
+        $(
+            if let Some(value) = $layer.$field {
+                $self.config.$field = value;
+                $self.origins.insert(stringify!($field), $origin.clone());
+            }
+        )*
+    };
+}
+
+impl LayeredConfig {
+    /// Override the values set in `layer`.
+    pub fn apply(&mut self, layer: Layer, origin: &Origin) {
+        apply_layer!(
+            self,
+            layer,
+            origin,
+            metadata_dir_name,
+            build_ignore_dirs,
+            compile_commands_name,
+            link_mode,
+            rewrites,
+            ignore,
+            format,
+            cache
+        );
+    }
+
+    pub fn origin(&self, field: &str) -> &Origin {
+        self.origins.get(field).unwrap_or(&Origin::Default)
+    }
+}
+
+/// The user configuration file, in `$XDG_CONFIG_HOME` or `~/.config`.
+pub fn user_config_path() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|config_dir| config_dir.join(USER_CONFIG_PATH))
+}
+
+/// The closest workspace configuration file in `start` or any folder above it.
+pub fn workspace_config_path(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|path| path.join(WORKSPACE_CONFIG_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Merge the built-in defaults, the user configuration, the workspace configuration found from
+/// `start`, and finally `command_line`.
+pub fn load(start: &Path, command_line: Layer) -> Result<LayeredConfig> {
+    let mut layered = LayeredConfig::default();
+
+    let files = [user_config_path(), workspace_config_path(start)];
+    for path in files.into_iter().flatten() {
+        let layer = Layer::read(&path)?;
+        layered.apply(layer, &Origin::File(path));
+    }
+    layered.apply(command_line, &Origin::CommandLine);
+
+    Ok(layered)
+}
+
+impl Display for LayeredConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let table = toml::Table::try_from(&self.config).map_err(|_| std::fmt::Error)?;
+        for (field, value) in table {
+            writeln!(f, "{field} = {value} # {}", self.origin(&field))?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for LayeredConfig {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Entry<'a> {
+            value: serde_json::Value,
+            origin: &'a Origin,
+        }
+
+        let serde_json::Value::Object(values) =
+            serde_json::to_value(&self.config).map_err(serde::ser::Error::custom)?
+        else {
+            return Err(serde::ser::Error::custom("configuration is not a map"));
+        };
+        serializer.collect_map(values.into_iter().map(|(field, value)| {
+            let origin = self.origin(&field);
+            (field, Entry { value, origin })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::{Config, Layer, LayeredConfig, Origin},
+        symlink::LinkMode,
+    };
+
+    #[test]
+    fn test_layers() {
+        let mut layered = LayeredConfig::default();
+        let user = PathBuf::from("/home/me/.config/symlinkccc/config.toml");
+        let workspace = PathBuf::from("/ws/.symlinkccc.toml");
+
+        let layer: Layer =
+            toml::from_str("link_mode = \"relative\"\nbuild_ignore_dirs = [\"build\", \"logs\"]\n")
+                .unwrap();
+        layered.apply(layer, &Origin::File(user.clone()));
+
+        let layer: Layer = toml::from_str(
+            "link_mode = \"copy\"\nrewrites = [{ from = \"/ws\", to = \"/home/me/ws\" }]\n",
+        )
+        .unwrap();
+        layered.apply(layer, &Origin::File(workspace.clone()));
+
+        assert_eq!(layered.config.link_mode, LinkMode::Copy);
+        assert_eq!(
+            layered.origin("link_mode"),
+            &Origin::File(workspace.clone())
+        );
+        assert_eq!(
+            layered.config.rewrites,
+            vec!["/ws=/home/me/ws".parse().unwrap()]
+        );
+        assert_eq!(layered.origin("rewrites"), &Origin::File(workspace));
+        assert_eq!(layered.config.build_ignore_dirs, vec!["build", "logs"]);
+        assert_eq!(layered.origin("build_ignore_dirs"), &Origin::File(user));
+        assert_eq!(
+            layered.config.compile_commands_name,
+            Config::default().compile_commands_name
+        );
+        assert_eq!(layered.origin("compile_commands_name"), &Origin::Default);
+
+        assert!(toml::from_str::<Layer>("unknown = 1\n").is_err());
+    }
+}
@@ -0,0 +1,308 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compile_commands::{self, CompileCommand},
+    paths::modified,
+};
+
+/// Replace the path prefix `from` by `to`, e.g. to translate paths inside a container to paths on
+/// the host.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct InvalidRewriteError(pub String);
+
+impl std::fmt::Display for InvalidRewriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(rewrite) = self;
+        write!(f, "Invalid rewrite '{rewrite}', expected FROM=TO")
+    }
+}
+
+impl std::error::Error for InvalidRewriteError {}
+
+impl FromStr for Rewrite {
+    type Err = InvalidRewriteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((from, to)) if !from.is_empty() => Ok(Self {
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            _ => Err(InvalidRewriteError(s.to_string())),
+        }
+    }
+}
+
+impl Rewrite {
+    fn path(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.from)
+            .ok()
+            .map(|rest| Path::new(&self.to).join(rest))
+    }
+
+    /// Whether the prefix matches a whole path component at byte `index` of `text`.
+    fn matches_at(&self, text: &str, index: usize) -> bool {
+        let Some(rest) = text[index..].strip_prefix(&self.from) else {
+            return false;
+        };
+        self.from.ends_with('/')
+            || rest
+                .chars()
+                .next()
+                .is_none_or(|c| c == '/' || is_path_boundary(c))
+    }
+}
+
+fn is_path_boundary(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '"' | '\'')
+}
+
+/// Whether a path may start at byte `index` of `text`: at the start of an argument, after `=`, or
+/// right after an `-I` or `-isystem` flag.
+fn starts_path(text: &str, index: usize) -> bool {
+    let before = &text[..index];
+    let after_flag = |flag: &str| {
+        before
+            .strip_suffix(flag)
+            .is_some_and(|rest| rest.chars().next_back().is_none_or(is_path_boundary))
+    };
+    before
+        .chars()
+        .next_back()
+        .is_none_or(|c| c == '=' || is_path_boundary(c))
+        || after_flag("-I")
+        || after_flag("-isystem")
+}
+
+fn rewrite_path(path: PathBuf, rewrites: &[Rewrite]) -> PathBuf {
+    rewrites
+        .iter()
+        .find_map(|rewrite| rewrite.path(&path))
+        .unwrap_or(path)
+}
+
+/// Rewrite the paths in `text` by the first matching prefix, leaving the rewritten paths alone.
+fn rewrite_text(text: &str, rewrites: &[Rewrite]) -> String {
+    let mut rewritten = String::with_capacity(text.len());
+    let mut index = 0;
+    while let Some(c) = text[index..].chars().next() {
+        if starts_path(text, index) {
+            if let Some(rewrite) = rewrites
+                .iter()
+                .find(|rewrite| rewrite.matches_at(text, index))
+            {
+                rewritten.push_str(&rewrite.to);
+                index += rewrite.from.len();
+                continue;
+            }
+        }
+        rewritten.push(c);
+        index += c.len_utf8();
+    }
+    rewritten
+}
+
+/// Apply `rewrites` to every path in `command`, including the paths in its command line. Each path
+/// is rewritten by the first matching prefix.
+pub fn rewrite_command(command: CompileCommand, rewrites: &[Rewrite]) -> CompileCommand {
+    CompileCommand {
+        directory: rewrite_path(command.directory, rewrites),
+        file: rewrite_path(command.file, rewrites),
+        command: command
+            .command
+            .map(|command| rewrite_text(&command, rewrites)),
+        arguments: command.arguments.map(|arguments| {
+            arguments
+                .iter()
+                .map(|argument| rewrite_text(argument, rewrites))
+                .collect()
+        }),
+        output: command.output.map(|output| rewrite_path(output, rewrites)),
+    }
+}
+
+/// Written next to every copy, marking it as created by symlinkccc and recording how.
+#[derive(Debug, PartialEq, Eq, Deserialize, Serialize)]
+struct Marker {
+    source: PathBuf,
+    rewrites: Vec<Rewrite>,
+}
+
+pub fn marker_path(compile_commands: &Path) -> PathBuf {
+    compile_commands.with_file_name(crate::config::COPY_MARKER_NAME)
+}
+
+/// Whether `compile_commands` is a copy made by symlinkccc.
+pub fn is_copy(compile_commands: &Path) -> bool {
+    marker_path(compile_commands).is_file()
+}
+
+fn read_marker(copy: &Path) -> Option<Marker> {
+    std::fs::read(marker_path(copy))
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+}
+
+/// The compile commands the copy at `copy` was made from.
+pub fn copied_from(copy: &Path) -> Option<PathBuf> {
+    read_marker(copy).map(|marker| marker.source)
+}
+
+/// Whether the copy at `copy` was made from `source` with `rewrites`, after `source` last changed.
+pub fn is_up_to_date(source: &Path, copy: &Path, rewrites: &[Rewrite]) -> bool {
+    let marker = read_marker(copy);
+    let expected = Marker {
+        source: source.to_path_buf(),
+        rewrites: rewrites.to_vec(),
+    };
+
+    marker.is_some_and(|marker| marker == expected) && modified(copy) >= modified(source)
+}
+
+/// Write a rewritten copy of the compile commands at `source` to `copy`.
+pub fn copy_compile_commands(
+    source: &Path,
+    copy: &Path,
+    rewrites: &[Rewrite],
+) -> compile_commands::Result<()> {
+    let commands: Vec<_> = compile_commands::read(source)?
+        .into_iter()
+        .map(|command| rewrite_command(command, rewrites))
+        .collect();
+
+    let marker = Marker {
+        source: source.to_path_buf(),
+        rewrites: rewrites.to_vec(),
+    };
+    std::fs::write(marker_path(copy), serde_json::to_vec_pretty(&marker)?)?;
+    compile_commands::write(copy, &commands)
+}
+
+/// Remove the copy at `copy` together with its marker.
+pub fn remove_copy(copy: &Path) -> std::io::Result<()> {
+    std::fs::remove_file(copy)?;
+    std::fs::remove_file(marker_path(copy))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        compile_commands::CompileCommand,
+        copy::{rewrite_command, Rewrite},
+    };
+
+    #[test]
+    fn test_rewrite_parse() {
+        assert_eq!(
+            "/ws=/home/me/ws".parse::<Rewrite>().unwrap(),
+            Rewrite {
+                from: "/ws".to_string(),
+                to: "/home/me/ws".to_string()
+            }
+        );
+        assert!("/ws".parse::<Rewrite>().is_err());
+        assert!("=/ws".parse::<Rewrite>().is_err());
+    }
+
+    #[test]
+    fn test_rewrite_command() {
+        let rewrites = vec!["/ws=/home/me/ws".parse().unwrap()];
+        let command = CompileCommand {
+            directory: "/ws/build/pkg".into(),
+            file: "/ws/src/pkg/a.cpp".into(),
+            command: None,
+            arguments: Some(vec![
+                "c++".to_string(),
+                "-I/ws/src/pkg/include".to_string(),
+                "-c".to_string(),
+                "/ws/src/pkg/a.cpp".to_string(),
+            ]),
+            output: Some("a.o".into()),
+        };
+
+        assert_eq!(
+            rewrite_command(command, &rewrites),
+            CompileCommand {
+                directory: "/home/me/ws/build/pkg".into(),
+                file: "/home/me/ws/src/pkg/a.cpp".into(),
+                command: None,
+                arguments: Some(vec![
+                    "c++".to_string(),
+                    "-I/home/me/ws/src/pkg/include".to_string(),
+                    "-c".to_string(),
+                    "/home/me/ws/src/pkg/a.cpp".to_string(),
+                ]),
+                output: Some("a.o".into()),
+            }
+        );
+
+        let command = CompileCommand {
+            directory: "/opt/build".into(),
+            file: "a.cpp".into(),
+            command: Some("c++ -I/ws/include -c a.cpp".to_string()),
+            arguments: None,
+            output: None,
+        };
+        assert_eq!(
+            rewrite_command(command, &rewrites).command,
+            Some("c++ -I/home/me/ws/include -c a.cpp".to_string())
+        );
+
+        let command = CompileCommand {
+            directory: "/srv/ws".into(),
+            file: "a.cpp".into(),
+            command: Some(
+                "c++ -I/srv/ws/include -isystem /wsx/include --sysroot=/ws -c /ws/a.cpp"
+                    .to_string(),
+            ),
+            arguments: Some(vec!["-I/srv/ws/include".to_string(), "/ws".to_string()]),
+            output: None,
+        };
+        let command = rewrite_command(command, &rewrites);
+        assert_eq!(command.directory, std::path::PathBuf::from("/srv/ws"));
+        assert_eq!(
+            command.command,
+            Some(
+                "c++ -I/srv/ws/include -isystem /wsx/include --sysroot=/home/me/ws -c /home/me/ws/a.cpp"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            command.arguments,
+            Some(vec![
+                "-I/srv/ws/include".to_string(),
+                "/home/me/ws".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_chained_rewrites() {
+        let rewrites: Vec<Rewrite> = vec!["/a=/b".parse().unwrap(), "/b=/c".parse().unwrap()];
+        let command = CompileCommand {
+            directory: "/a/build".into(),
+            file: "/b/a.cpp".into(),
+            command: Some("c++ -I/a/include -I/b/include /b/a.cpp".to_string()),
+            arguments: None,
+            output: None,
+        };
+        let command = rewrite_command(command, &rewrites);
+        assert_eq!(command.directory, std::path::PathBuf::from("/b/build"));
+        assert_eq!(command.file, std::path::PathBuf::from("/c/a.cpp"));
+        assert_eq!(
+            command.command,
+            Some("c++ -I/b/include -I/c/include /c/a.cpp".to_string())
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use std::{collections::BTreeSet, fmt::Display, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    manifest::BuildType,
+    package_name::PackageName,
+    paths::{
+        package_container::PackageContainer,
+        path::Path,
+        structs::{Build, Source, SourcePackage, Workspace},
+    },
+    workspace_kind::WorkspaceKind,
+};
+
+/// A package found among the source packages, the built packages, or both.
+#[derive(Debug, Serialize)]
+pub struct DiscoveredPackage {
+    #[serde(rename = "package")]
+    pub package_name: PackageName,
+    /// How the source package is built, where anything but CMake never has compile commands.
+    pub build_type: Option<BuildType>,
+    pub source: Option<PathBuf>,
+    pub build: Option<PathBuf>,
+}
+
+/// A workspace and every package in it.
+#[derive(Debug, Serialize)]
+pub struct Discovered {
+    pub workspace: PathBuf,
+    pub kind: WorkspaceKind,
+    pub profile: Option<String>,
+    pub source_space: PathBuf,
+    pub build_space: PathBuf,
+    pub packages: Vec<DiscoveredPackage>,
+}
+
+fn display_optional(path: Option<&PathBuf>) -> String {
+    path.map_or_else(|| "-".to_string(), |path| path.display().to_string())
+}
+
+impl Display for Discovered {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} workspace at {}", self.kind, self.workspace.display())?;
+        if let Some(profile) = &self.profile {
+            write!(f, " (profile '{profile}')")?;
+        }
+        writeln!(f)?;
+        writeln!(f, "source space: {}", self.source_space.display())?;
+        writeln!(f, "build space:  {}", self.build_space.display())?;
+        writeln!(f)?;
+        for package in &self.packages {
+            write!(f, "{}", package.package_name)?;
+            if let Some(build_type) = &package.build_type {
+                write!(f, " ({build_type})")?;
+            }
+            writeln!(
+                f,
+                ": {} -> {}",
+                display_optional(package.source.as_ref()),
+                display_optional(package.build.as_ref())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Find every source and built package of `workspace`, without writing the discovery cache in a
+/// `dry_run`.
+pub fn discover(workspace: &Workspace, dry_run: bool) -> Discovered {
+    log::debug!("Current workspace: {workspace}");
+
+    let source = Source::from(workspace);
+    let build = Build::from(workspace);
+    let source_packages = crate::cache::source_packages(workspace, dry_run);
+    let build_packages = build.get_all_package_paths(&workspace.config);
+
+    let package_names: BTreeSet<_> = source_packages
+        .keys()
+        .chain(build_packages.keys())
+        .cloned()
+        .collect();
+
+    Discovered {
+        workspace: workspace.path(),
+        kind: workspace.kind,
+        profile: workspace
+            .profile
+            .as_ref()
+            .map(|profile| profile.name.clone()),
+        source_space: source.path(),
+        build_space: build.path(),
+        packages: package_names
+            .into_iter()
+            .map(|package_name| DiscoveredPackage {
+                build_type: source_packages
+                    .get(&package_name)
+                    .and_then(SourcePackage::build_type),
+                source: source_packages.get(&package_name).map(Path::path),
+                build: build_packages.get(&package_name).map(Path::path),
+                package_name,
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,302 @@
+use std::{fmt::Display, path::PathBuf};
+
+use crate::{
+    manifest::BuildType,
+    package_name::PackageName,
+    parsers::cmake_cache::{cmake_cache_parser, is_true},
+    paths::{
+        path::Path,
+        structs::{Build, BuildCompileCommands, SourcePackage, Workspace},
+    },
+    workspace_kind::WorkspaceKind,
+};
+
+const EXPORT_COMPILE_COMMANDS: &str = "CMAKE_EXPORT_COMPILE_COMMANDS";
+
+/// Why a package does or does not have a compile commands database.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// The package has a compile commands database.
+    Ok,
+    /// The package is not built with CMake, so it never has a compile commands database.
+    NotApplicable(BuildType),
+    /// The package has no build folder.
+    NotBuilt,
+    /// The package has a build folder, but CMake has not configured it.
+    NotConfigured,
+    /// CMake was configured without exporting compile commands.
+    ExportDisabled,
+    /// The profile exports compile commands, but the package was configured before that.
+    ExportNotApplied,
+    /// CMake exports compile commands, but the build has not generated them (yet).
+    BuildIncomplete,
+}
+
+impl Diagnosis {
+    pub fn is_problem(&self) -> bool {
+        !matches!(self, Self::Ok | Self::NotApplicable(_))
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::NotApplicable(_) => "n/a",
+            Self::NotBuilt
+            | Self::NotConfigured
+            | Self::ExportDisabled
+            | Self::ExportNotApplied
+            | Self::BuildIncomplete => "problem",
+        }
+    }
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ok => write!(f, "has a compile commands database"),
+            Self::NotApplicable(build_type) => {
+                write!(
+                    f,
+                    "is a {build_type} package, which has no compile commands"
+                )
+            }
+            Self::NotBuilt => write!(f, "has not been built"),
+            Self::NotConfigured => write!(
+                f,
+                "has a build folder, but no {}",
+                crate::config::CMAKE_CACHE_NAME
+            ),
+            Self::ExportDisabled => write!(f, "was configured without {EXPORT_COMPILE_COMMANDS}"),
+            Self::ExportNotApplied => write!(
+                f,
+                "was configured before {EXPORT_COMPILE_COMMANDS} was added to the profile"
+            ),
+            Self::BuildIncomplete => write!(
+                f,
+                "exports compile commands, but the build did not finish generating them"
+            ),
+        }
+    }
+}
+
+/// The diagnosis of a single package, with the commands that would fix it.
+#[derive(Debug)]
+pub struct Diagnosed {
+    pub package_name: PackageName,
+    pub diagnosis: Diagnosis,
+    pub remediation: Vec<String>,
+}
+
+impl Display for Diagnosed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<9} {} {}",
+            self.diagnosis.label(),
+            self.package_name,
+            self.diagnosis
+        )?;
+        for command in &self.remediation {
+            write!(f, "\n{:<9} $ {command}", "")?;
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for Diagnosed {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Record<'a> {
+            package: &'a PackageName,
+            status: &'static str,
+            diagnosis: String,
+            remediation: &'a [String],
+        }
+
+        Record {
+            package: &self.package_name,
+            status: self.diagnosis.label(),
+            diagnosis: self.diagnosis.to_string(),
+            remediation: &self.remediation,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Whether `cmake_args` set `CMAKE_EXPORT_COMPILE_COMMANDS` to a true value.
+fn exports_compile_commands(cmake_args: &[String]) -> bool {
+    cmake_args
+        .iter()
+        .filter_map(|arg| arg.strip_prefix("-D")?.split_once('='))
+        .rfind(|(key, _)| key.split(':').next() == Some(EXPORT_COMPILE_COMMANDS))
+        .is_some_and(|(_, value)| is_true(value))
+}
+
+fn diagnose(
+    workspace: &Workspace,
+    package_name: &PackageName,
+    source_package: &SourcePackage,
+) -> Diagnosis {
+    if let Some(build_type) = source_package.build_type() {
+        if !build_type.uses_cmake() {
+            return Diagnosis::NotApplicable(build_type);
+        }
+    }
+
+    let (build_path, compile_commands) = match workspace.kind {
+        // catkin_make configures all packages together, into a single database.
+        WorkspaceKind::CatkinMake => {
+            let compile_commands = BuildCompileCommands::from(workspace).path();
+            let build_path = compile_commands
+                .parent()
+                .map(std::path::Path::to_path_buf)
+                .unwrap_or_default();
+            (build_path, compile_commands)
+        }
+        WorkspaceKind::CatkinTools
+        | WorkspaceKind::Colcon
+        | WorkspaceKind::CatkinMakeIsolated
+        | WorkspaceKind::Custom => {
+            let build_path = Build::from(workspace).path().join(&package_name.0);
+            let compile_commands = build_path.join(&workspace.config.compile_commands_name);
+            (build_path, compile_commands)
+        }
+    };
+
+    if compile_commands.is_file() {
+        return Diagnosis::Ok;
+    }
+    if !build_path.is_dir() {
+        return Diagnosis::NotBuilt;
+    }
+
+    let cache_path = build_path.join(crate::config::CMAKE_CACHE_NAME);
+    let Ok(cache) = std::fs::read_to_string(&cache_path) else {
+        log::debug!("Cannot read {}", cache_path.display());
+        return Diagnosis::NotConfigured;
+    };
+
+    let cache = cmake_cache_parser(&cache);
+    if cache
+        .get(EXPORT_COMPILE_COMMANDS)
+        .is_some_and(|value| is_true(value))
+    {
+        Diagnosis::BuildIncomplete
+    } else if workspace
+        .profile
+        .as_ref()
+        .is_some_and(|profile| exports_compile_commands(&profile.cmake_args))
+    {
+        Diagnosis::ExportNotApplied
+    } else {
+        Diagnosis::ExportDisabled
+    }
+}
+
+fn export_argument() -> String {
+    format!("-D{EXPORT_COMPILE_COMMANDS}=ON")
+}
+
+/// The commands that fix `diagnosis` for the package `package_name` in `workspace`.
+fn remediation(
+    workspace: &Workspace,
+    package_name: &PackageName,
+    diagnosis: &Diagnosis,
+) -> Vec<String> {
+    let PackageName(name) = package_name;
+    let profile = workspace
+        .profile
+        .as_ref()
+        .map(|profile| format!(" --profile {}", profile.name))
+        .unwrap_or_default();
+
+    match (diagnosis, workspace.kind) {
+        (Diagnosis::Ok | Diagnosis::NotApplicable(_), _) => vec![],
+        (Diagnosis::NotBuilt | Diagnosis::NotConfigured | Diagnosis::BuildIncomplete, kind) => {
+            match kind {
+                WorkspaceKind::CatkinTools => vec![format!("catkin build{profile} {name}")],
+                WorkspaceKind::Colcon => vec![format!("colcon build --packages-select {name}")],
+                WorkspaceKind::CatkinMakeIsolated => {
+                    vec![format!("catkin_make_isolated --pkg {name}")]
+                }
+                WorkspaceKind::CatkinMake => {
+                    vec![format!("catkin_make --only-pkg-with-deps {name}")]
+                }
+                WorkspaceKind::Custom => vec![],
+            }
+        }
+        (Diagnosis::ExportNotApplied, _) => {
+            vec![format!("catkin build{profile} --force-cmake {name}")]
+        }
+        (Diagnosis::ExportDisabled, WorkspaceKind::CatkinTools) => vec![
+            format!(
+                "catkin config{profile} --append-args --cmake-args {}",
+                export_argument()
+            ),
+            format!("catkin build{profile} --force-cmake {name}"),
+        ],
+        (Diagnosis::ExportDisabled, WorkspaceKind::Colcon) => vec![format!(
+            "colcon build --packages-select {name} --cmake-args {}",
+            export_argument()
+        )],
+        (Diagnosis::ExportDisabled, WorkspaceKind::CatkinMakeIsolated) => vec![format!(
+            "catkin_make_isolated --pkg {name} --cmake-args {}",
+            export_argument()
+        )],
+        (Diagnosis::ExportDisabled, WorkspaceKind::CatkinMake) => {
+            vec![format!("catkin_make {}", export_argument())]
+        }
+        (Diagnosis::ExportDisabled, WorkspaceKind::Custom) => {
+            let build_path: PathBuf = Build::from(workspace).path().join(name);
+            vec![format!(
+                "cmake {} {}",
+                export_argument(),
+                build_path.display()
+            )]
+        }
+    }
+}
+
+/// Diagnose every source package in `workspace`, without writing the discovery cache in a
+/// `dry_run`.
+pub fn diagnose_all_packages(workspace: &Workspace, dry_run: bool) -> Vec<Diagnosed> {
+    log::debug!("Current workspace: {workspace}");
+
+    crate::cache::source_packages(workspace, dry_run)
+        .into_iter()
+        .map(|(package_name, source_package)| {
+            let diagnosis = diagnose(workspace, &package_name, &source_package);
+            let remediation = remediation(workspace, &package_name, &diagnosis);
+            Diagnosed {
+                package_name,
+                diagnosis,
+                remediation,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::doctor::exports_compile_commands;
+
+    #[test]
+    fn test_exports_compile_commands() {
+        let args = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert!(exports_compile_commands(&args(&[
+            "-DCMAKE_BUILD_TYPE=Release",
+            "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON"
+        ])));
+        assert!(exports_compile_commands(&args(&[
+            "-DCMAKE_EXPORT_COMPILE_COMMANDS:BOOL=1"
+        ])));
+        assert!(!exports_compile_commands(&args(&[
+            "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON",
+            "-DCMAKE_EXPORT_COMPILE_COMMANDS=OFF"
+        ])));
+        assert!(!exports_compile_commands(&args(&[
+            "-DCMAKE_BUILD_TYPE=Release"
+        ])));
+    }
+}
@@ -0,0 +1,123 @@
+use std::fmt::Display;
+
+use crate::{compile_commands, config, manifest, profile, symlink, watch, workspace};
+
+/// Any error returned by symlinkccc.
+#[derive(Debug)]
+pub enum Error {
+    Config(config::Error),
+    Workspace(workspace::Error),
+    Profile(profile::Error),
+    Link(symlink::Error),
+    CompileCommands(compile_commands::Error),
+    Watch(watch::Error),
+    Manifest(manifest::Error),
+    IO(std::io::Error),
+}
+
+impl From<config::Error> for Error {
+    fn from(value: config::Error) -> Self {
+        Self::Config(value)
+    }
+}
+
+impl From<workspace::Error> for Error {
+    fn from(value: workspace::Error) -> Self {
+        Self::Workspace(value)
+    }
+}
+
+impl From<profile::Error> for Error {
+    fn from(value: profile::Error) -> Self {
+        Self::Profile(value)
+    }
+}
+
+impl From<symlink::Error> for Error {
+    fn from(value: symlink::Error) -> Self {
+        Self::Link(value)
+    }
+}
+
+impl From<compile_commands::Error> for Error {
+    fn from(value: compile_commands::Error) -> Self {
+        Self::CompileCommands(value)
+    }
+}
+
+impl From<watch::Error> for Error {
+    fn from(value: watch::Error) -> Self {
+        Self::Watch(value)
+    }
+}
+
+impl From<manifest::Error> for Error {
+    fn from(value: manifest::Error) -> Self {
+        Self::Manifest(value)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Config(err) => write!(f, "{err}"),
+            Self::Workspace(err) => write!(f, "{err}"),
+            Self::Profile(err) => write!(f, "{err}"),
+            Self::Link(err) => write!(f, "{err}"),
+            Self::CompileCommands(err) => write!(f, "{err}"),
+            Self::Watch(err) => write!(f, "{err}"),
+            Self::Manifest(err) => write!(f, "{err}"),
+            Self::IO(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    /// The error of the module which failed, so callers can inspect the cause.
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Config(err) => Some(err),
+            Self::Workspace(err) => Some(err),
+            Self::Profile(err) => Some(err),
+            Self::Link(err) => Some(err),
+            Self::CompileCommands(err) => Some(err),
+            Self::Watch(err) => Some(err),
+            Self::Manifest(err) => Some(err),
+            Self::IO(err) => Some(err),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use crate::{error::Error, workspace};
+
+    #[test]
+    fn test_source() {
+        let err = Error::from(std::io::Error::from(std::io::ErrorKind::NotFound));
+        let io_err = err
+            .source()
+            .and_then(|source| source.downcast_ref::<std::io::Error>());
+        assert_eq!(
+            io_err.map(std::io::Error::kind),
+            Some(std::io::ErrorKind::NotFound)
+        );
+
+        let err = Error::from(workspace::Error::from(std::io::Error::from(
+            std::io::ErrorKind::PermissionDenied,
+        )));
+        assert!(err
+            .source()
+            .is_some_and(|source| source.is::<workspace::Error>()));
+    }
+}
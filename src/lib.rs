@@ -0,0 +1,75 @@
+//! Link the compile commands of built catkin, colcon and plain CMake packages into their source
+//! folders.
+//!
+//! A [`Workspace`] is located with [`find_enclosing`] or [`open`], after which its source and
+//! built packages can be enumerated through [`PackageContainer`], and their compile commands be
+//! linked, unlinked, merged or diagnosed:
+//!
+//! ```no_run
+//! use symlinkccc::{find_enclosing, link_all_compile_commands, LinkOptions, WorkspaceOptions};
+//!
+//! let workspace = find_enclosing("/home/me/ws/src/foo", &WorkspaceOptions::default())?;
+//! let report = link_all_compile_commands(&workspace, &LinkOptions::default())?;
+//! print!("{}", report.summary());
+//! # Ok::<(), symlinkccc::Error>(())
+//! ```
+//!
+//! Every module has its own error type, all of which convert into the crate wide [`Error`].
+
+/// Caching the source packages found between runs.
+pub mod cache;
+/// The contents of a compile commands database.
+pub mod compile_commands;
+/// The runtime configuration, and the file and folder names used by the build tools.
+pub mod config;
+/// Copying compile commands into source packages, rewriting their paths.
+pub mod copy;
+/// Listing the source and built packages of a workspace.
+pub mod discover;
+/// Diagnosing why packages have no compile commands.
+pub mod doctor;
+mod error;
+/// The contents of `package.xml` files.
+pub mod manifest;
+/// Merging the compile commands of all packages into a single database.
+pub mod merge;
+/// Printing results as text or JSON.
+pub mod output;
+/// The name of a package.
+pub mod package_name;
+/// Parsers for `package.xml`, `CMakeLists.txt`, `CMakeCache.txt` and catkin profiles.
+pub mod parsers;
+/// Typed paths to workspaces, packages and the files within them.
+pub mod paths;
+/// catkin_tools profiles.
+pub mod profile;
+/// The outcome of linking a whole workspace.
+pub mod report;
+/// Splitting the single database of catkin_make into one database per package.
+pub mod split;
+/// The state of the compile commands of each package.
+pub mod status;
+/// Linking compile commands into source packages.
+pub mod symlink;
+/// Removing the compile commands linked by symlinkccc.
+pub mod unlink;
+/// Relinking packages as their compile commands change.
+pub mod watch;
+/// Locating workspaces.
+pub mod workspace;
+/// The build tools whose workspace layouts are understood.
+pub mod workspace_kind;
+
+pub use error::{Error, Result};
+pub use package_name::PackageName;
+pub use paths::{
+    package::Package,
+    package_container::PackageContainer,
+    path::Path,
+    structs::{Build, BuildPackage, Source, SourcePackage, Workspace},
+};
+pub use report::LinkReport;
+pub use symlink::{link_all_compile_commands, LinkMode, LinkOptions};
+pub use unlink::unlink_all_compile_commands;
+pub use workspace::{find_enclosing, open, WorkspaceOptions};
+pub use workspace_kind::WorkspaceKind;
@@ -1,36 +1,449 @@
-mod config;
-mod package_name;
-mod parsers;
-mod paths;
-mod symlink;
-mod workspace;
+use std::{path::PathBuf, process::ExitCode, time::Duration};
 
-use symlink::link_all_compile_commands;
+use symlinkccc::{
+    cache,
+    config::{self, Config, Layer},
+    copy::Rewrite,
+    discover::discover,
+    doctor::diagnose_all_packages,
+    link_all_compile_commands,
+    merge::{merge_all_compile_commands, MergeTarget},
+    output::{self, Format},
+    report::ExitPolicy,
+    status::{status_all_packages, StatusTable},
+    unlink::Outcome,
+    unlink_all_compile_commands,
+    watch::watch_compile_commands,
+    workspace, LinkMode, LinkOptions, Workspace, WorkspaceOptions,
+};
 
-use clap::Parser;
+use clap::{
+    error::ErrorKind, parser::ValueSource, ArgMatches, Args, CommandFactory, FromArgMatches,
+    Parser, Subcommand,
+};
 use clap_verbosity_flag::Verbosity;
 
-/// Foo
+/// Symbolically link the compile commands of built catkin packages into their source folders
 #[derive(Debug, Parser)]
 struct Cli {
     #[clap(flatten)]
     verbose: Verbosity,
+
+    /// Link against the build space of this catkin profile instead of the active one
+    #[clap(long, global = true)]
+    profile: Option<String>,
+
+    /// Use this workspace instead of searching upwards from the current directory
+    #[clap(long, short, global = true)]
+    workspace: Option<PathBuf>,
+
+    /// Build space to link from, relative to the workspace root
+    #[clap(long, global = true)]
+    build_dir: Option<PathBuf>,
+
+    /// Source space to link into, relative to the workspace root
+    #[clap(long, global = true)]
+    source_dir: Option<PathBuf>,
+
+    /// Print what would be done without touching the filesystem
+    #[clap(long, global = true)]
+    dry_run: bool,
+
+    /// Search the source space for packages, instead of reusing what the previous run found
+    #[clap(long, global = true)]
+    no_cache: bool,
+
+    /// Skip source folders matching this glob, relative to the source space, instead of the
+    /// configured ones
+    #[clap(long, value_name = "GLOB", global = true)]
+    ignore: Vec<String>,
+
+    /// How to print the results
+    #[clap(long, value_enum, global = true)]
+    format: Option<Format>,
+
+    #[clap(flatten)]
+    link: LinkArgs,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Args)]
+struct LinkArgs {
+    /// Back up and replace compile commands files which were not created by symlinkccc
+    #[clap(long)]
+    force: bool,
+
+    /// Link relative to the source package, so the links survive moving the workspace
+    #[clap(long, conflicts_with = "copy")]
+    relative: bool,
+
+    /// Copy the compile commands instead of linking them, regenerating outdated copies
+    #[clap(long)]
+    copy: bool,
+
+    /// Rewrite a path prefix in copied compile commands, e.g. `/ws=/home/me/ws`
+    #[clap(long, value_name = "FROM=TO")]
+    rewrite: Vec<Rewrite>,
+
+    /// Which package outcomes make symlinkccc exit with a non-zero code
+    #[clap(long, value_enum, default_value_t)]
+    fail_on: ExitPolicy,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Link the compile commands of every built package into its source package (default)
+    Link(LinkArgs),
+    /// Remove the compile commands links created by symlinkccc from the source packages
+    #[clap(alias = "clean")]
+    Unlink,
+    /// Merge the compile commands of all built packages into a single workspace wide database
+    Merge(MergeArgs),
+    /// Keep linking packages as their compile commands appear, change or disappear
+    Watch(WatchArgs),
+    /// Show the state of the compile commands of every package
+    Status,
+    /// Diagnose why packages have no compile commands, and suggest how to fix it
+    Doctor,
+    /// List the workspace and its source and built packages
+    Discover,
+    /// Inspect the configuration of symlinkccc
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    /// Manage the cache of the packages found in the source space
+    #[clap(subcommand)]
+    Cache(CacheCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommand {
+    /// Remove the cache, so that the next run searches the whole source space again
+    Clear,
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration, and where each value came from
+    Show,
+}
+
+#[derive(Debug, Args)]
+struct WatchArgs {
+    #[clap(flatten)]
+    link: LinkArgs,
+
+    /// Milliseconds without changes to wait for before relinking
+    #[clap(long, default_value_t = 500)]
+    debounce: u64,
+}
+
+#[derive(Debug, Args)]
+struct MergeArgs {
+    /// Where to write the merged compile commands
+    #[clap(long, value_enum, default_value_t)]
+    into: MergeTarget,
+}
+
+/// The arguments of the default `link` command, which only apply without a subcommand.
+const TOP_LEVEL_LINK_ARGS: [&str; 5] = ["force", "relative", "copy", "rewrite", "fail_on"];
+
+impl Cli {
+    /// Parse `args` like [`Parser::try_parse_from`], but reject the arguments of the default
+    /// `link` command when a subcommand is given.
+    fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let mut command = Self::command();
+        let matches = command.try_get_matches_from_mut(args)?;
+        if let Some((subcommand, _)) = matches.subcommand() {
+            if let Some(id) = Self::given_link_arg(&matches) {
+                return Err(command.error(
+                    ErrorKind::ArgumentConflict,
+                    format!(
+                        "'--{}' cannot be used with the subcommand '{subcommand}'",
+                        id.replace('_', "-")
+                    ),
+                ));
+            }
+        }
+        Self::from_arg_matches(&matches)
+    }
+
+    fn given_link_arg(matches: &ArgMatches) -> Option<&'static str> {
+        TOP_LEVEL_LINK_ARGS
+            .into_iter()
+            .find(|id| matches.value_source(id) == Some(ValueSource::CommandLine))
+    }
+}
+
+impl LinkArgs {
+    fn mode(&self) -> Option<LinkMode> {
+        if self.copy {
+            Some(LinkMode::Copy)
+        } else if self.relative {
+            Some(LinkMode::Relative)
+        } else {
+            None
+        }
+    }
+}
+
+/// The configuration given on the command line, which overrides every configuration file.
+fn command_line_layer(cli: &Cli) -> Layer {
+    let link_args = match &cli.command {
+        Some(Command::Link(args)) => args,
+        Some(Command::Watch(args)) => &args.link,
+        _ => &cli.link,
+    };
+
+    Layer {
+        link_mode: link_args.mode(),
+        rewrites: (!link_args.rewrite.is_empty()).then(|| link_args.rewrite.clone()),
+        ignore: (!cli.ignore.is_empty()).then(|| cli.ignore.clone()),
+        format: cli.format,
+        cache: cli.no_cache.then_some(false),
+        ..Layer::default()
+    }
+}
+
+fn load_config(cli: &Cli) -> symlinkccc::Result<config::LayeredConfig> {
+    let start = match &cli.workspace {
+        Some(workspace_path) => workspace_path.clone(),
+        None => std::env::current_dir().map_err(workspace::Error::from)?,
+    };
+    Ok(config::load(&start, command_line_layer(cli))?)
+}
+
+fn locate_workspace(cli: &Cli, config: Config) -> workspace::Result<Workspace> {
+    let options = WorkspaceOptions {
+        profile: cli.profile.clone(),
+        build_space: cli.build_dir.clone(),
+        source_space: cli.source_dir.clone(),
+        config,
+    };
+
+    if let Some(workspace_path) = &cli.workspace {
+        workspace::open(workspace_path, &options)
+    } else {
+        let current_working_directory = std::env::current_dir()?;
+        log::debug!(
+            "Current working directory: {}",
+            current_working_directory.display()
+        );
+        workspace::find_enclosing(current_working_directory, &options)
+    }
+}
+
+/// The options of the `link` and `watch` commands. Rewrites given on the command line are
+/// rejected unless the effective link mode copies the compile commands.
+fn link_options(
+    args: &LinkArgs,
+    dry_run: bool,
+    config: &Config,
+) -> Result<LinkOptions, clap::Error> {
+    let mode = args.mode().unwrap_or(config.link_mode);
+    if !args.rewrite.is_empty() && mode != LinkMode::Copy {
+        return Err(Cli::command().error(
+            ErrorKind::ArgumentConflict,
+            "'--rewrite' only applies to copies, pass '--copy' or set link_mode = \"copy\"",
+        ));
+    }
+
+    Ok(LinkOptions {
+        dry_run,
+        force: args.force,
+        mode,
+        rewrites: config.rewrites.clone(),
+    })
+}
+
+fn unlink(workspace: &Workspace, dry_run: bool, format: Format) -> ExitCode {
+    let unlinked = unlink_all_compile_commands(workspace, dry_run);
+    output::print_all(format, &unlinked);
+
+    if unlinked
+        .iter()
+        .any(|unlinked| matches!(unlinked.outcome, Outcome::Failed(_)))
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn link(
+    workspace: &Workspace,
+    options: &LinkOptions,
+    policy: ExitPolicy,
+    format: Format,
+) -> ExitCode {
+    match link_all_compile_commands(workspace, options) {
+        Ok(report) => {
+            output::print(format, &report);
+
+            if report.fails(policy) {
+                ExitCode::FAILURE
+            } else {
+                log::info!("Linking completed successfully");
+                ExitCode::SUCCESS
+            }
+        }
+        Err(err) => {
+            log::error!("Cannot link compile commands:\n{err}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-fn main() {
+fn merge(workspace: &Workspace, args: &MergeArgs, dry_run: bool, format: Format) -> ExitCode {
+    match merge_all_compile_commands(workspace, args.into, dry_run) {
+        Ok(merged) => {
+            output::print(format, &merged);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            log::error!("Cannot merge compile commands:\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn watch(workspace: &Workspace, args: &WatchArgs, dry_run: bool, format: Format) -> ExitCode {
+    let options =
+        link_options(&args.link, dry_run, workspace.config()).unwrap_or_else(|err| err.exit());
+    match watch_compile_commands(
+        workspace,
+        &options,
+        Duration::from_millis(args.debounce),
+        format,
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            log::error!("Cannot watch compile commands:\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn status(workspace: &Workspace, dry_run: bool, format: Format) -> ExitCode {
+    let statuses = status_all_packages(workspace, dry_run);
+    output::print(format, &StatusTable(&statuses));
+    ExitCode::SUCCESS
+}
+
+fn doctor(workspace: &Workspace, dry_run: bool, format: Format) -> ExitCode {
+    let diagnosed = diagnose_all_packages(workspace, dry_run);
+    output::print_all(format, &diagnosed);
+
+    if diagnosed
+        .iter()
+        .any(|diagnosed| diagnosed.diagnosis.is_problem())
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn clear_cache(workspace: &Workspace, format: Format) -> ExitCode {
+    match cache::clear(workspace) {
+        Ok(cleared) => {
+            output::print(format, &cleared);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            log::error!("Cannot remove the cache:\n{err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn main() -> ExitCode {
     log::trace!("Starting symlinkccc");
-    let cli = Cli::parse();
+    let cli = Cli::try_parse_args(std::env::args_os()).unwrap_or_else(|err| err.exit());
 
     env_logger::Builder::new()
         .filter_level(cli.verbose.log_level_filter())
         .init();
 
-    match link_all_compile_commands() {
-        Ok(()) => {
-            log::info!("Linking completed successfully");
+    let layered = match load_config(&cli) {
+        Ok(layered) => layered,
+        Err(err) => {
+            log::error!("Cannot load configuration:\n{err}");
+            return ExitCode::FAILURE;
         }
+    };
+    let format = layered.config.format;
+
+    if let Some(Command::Config(ConfigCommand::Show)) = cli.command {
+        output::print(format, &layered);
+        return ExitCode::SUCCESS;
+    }
+    let workspace = match locate_workspace(&cli, layered.config) {
+        Ok(workspace) => workspace,
         Err(err) => {
-            log::error!("Cannot link compile commands:\n{err:?}");
+            log::error!("Cannot find workspace:\n{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cli.command.unwrap_or(Command::Link(cli.link)) {
+        Command::Link(args) => {
+            let options = link_options(&args, cli.dry_run, workspace.config())
+                .unwrap_or_else(|err| err.exit());
+            link(&workspace, &options, args.fail_on, format)
         }
+        Command::Unlink => unlink(&workspace, cli.dry_run, format),
+        Command::Merge(args) => merge(&workspace, &args, cli.dry_run, format),
+        Command::Watch(args) => watch(&workspace, &args, cli.dry_run, format),
+        Command::Status => status(&workspace, cli.dry_run, format),
+        Command::Doctor => doctor(&workspace, cli.dry_run, format),
+        Command::Discover => {
+            output::print(format, &discover(&workspace, cli.dry_run));
+            ExitCode::SUCCESS
+        }
+        Command::Cache(CacheCommand::Clear) => clear_cache(&workspace, format),
+        Command::Config(ConfigCommand::Show) => {
+            unreachable!("handled before locating the workspace")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use symlinkccc::config::Config;
+
+    use crate::{link_options, Cli, Command};
+
+    #[test]
+    fn test_global_args_before_subcommand() {
+        let cli = Cli::try_parse_args(["symlinkccc", "-w", "ws", "status"]).unwrap();
+        assert_eq!(cli.workspace, Some("ws".into()));
+        assert!(matches!(cli.command, Some(Command::Status)));
+
+        let cli = Cli::try_parse_args(["symlinkccc", "--dry-run", "unlink"]).unwrap();
+        assert!(cli.dry_run);
+        assert!(matches!(cli.command, Some(Command::Unlink)));
+
+        let cli = Cli::try_parse_args(["symlinkccc", "--format", "json", "status"]).unwrap();
+        assert!(cli.format.is_some());
+
+        assert!(Cli::try_parse_args(["symlinkccc", "--force", "status"]).is_err());
+        assert!(Cli::try_parse_args(["symlinkccc", "--force"]).is_ok());
+        assert!(Cli::try_parse_args(["symlinkccc", "link", "--force"]).is_ok());
+    }
+
+    #[test]
+    fn test_rewrite_requires_copy() {
+        let cli = Cli::try_parse_args(["symlinkccc", "--rewrite", "/ws=/home/me/ws"]).unwrap();
+        assert!(link_options(&cli.link, false, &Config::default()).is_err());
+
+        let cli =
+            Cli::try_parse_args(["symlinkccc", "--copy", "--rewrite", "/ws=/home/me/ws"]).unwrap();
+        assert!(link_options(&cli.link, false, &Config::default()).is_ok());
     }
 }
@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, fmt::Display, path::Path};
+
+use crate::parsers::{condition::condition_parser, package::package_manifest_parser};
+
+/// The variables `condition` attributes are evaluated against.
+pub const CONDITION_VARIABLES: [&str; 2] = ["ROS_VERSION", "ROS_DISTRO"];
+
+#[derive(Debug)]
+pub enum Error {
+    IO(std::io::Error),
+    Xml(quick_xml::Error),
+    /// The manifest lacks a required element.
+    MissingElement(&'static str),
+    UnsupportedFormat(String),
+    InvalidCondition(String),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl From<quick_xml::Error> for Error {
+    fn from(value: quick_xml::Error) -> Self {
+        Self::Xml(value)
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for Error {
+    fn from(value: quick_xml::events::attributes::AttrError) -> Self {
+        Self::Xml(value.into())
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IO(err) => write!(f, "IO error: {err}"),
+            Self::Xml(err) => write!(f, "Invalid XML: {err}"),
+            Self::MissingElement(element) => write!(f, "Missing <{element}> element"),
+            Self::UnsupportedFormat(format) => write!(f, "Unsupported package format '{format}'"),
+            Self::InvalidCondition(condition) => write!(f, "Invalid condition '{condition}'"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The values of the variables `condition` attributes refer to.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Conditions(pub BTreeMap<String, String>);
+
+impl Conditions {
+    /// The condition variables of the current environment, e.g. of a sourced ROS installation.
+    pub fn from_env() -> Self {
+        Self(
+            CONDITION_VARIABLES
+                .into_iter()
+                .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+                .collect(),
+        )
+    }
+
+    /// Whether `condition` holds, where no condition always holds.
+    pub fn evaluate(&self, condition: Option<&str>) -> Result<bool> {
+        let Some(condition) = condition else {
+            return Ok(true);
+        };
+        let Self(variables) = self;
+        condition_parser(condition)
+            .map(|parsed| parsed.evaluate(&|name| variables.get(name).cloned()))
+            .ok_or_else(|| Error::InvalidCondition(condition.to_string()))
+    }
+}
+
+/// The kinds of dependencies a package can declare.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DependencyKind {
+    /// `<depend>`, short for a build, build export and exec dependency.
+    Depend,
+    Build,
+    BuildExport,
+    Buildtool,
+    BuildtoolExport,
+    Exec,
+    /// `<run_depend>` of format 1, replaced by build export and exec dependencies since.
+    Run,
+    Test,
+    Doc,
+}
+
+impl DependencyKind {
+    /// The kind of dependency declared by the element `tag` in a manifest of `format`.
+    pub fn from_tag(tag: &str, format: u8) -> Option<Self> {
+        match (tag, format) {
+            ("build_depend", _) => Some(Self::Build),
+            ("buildtool_depend", _) => Some(Self::Buildtool),
+            ("test_depend", _) => Some(Self::Test),
+            ("run_depend", 1) => Some(Self::Run),
+            ("depend", 2..) => Some(Self::Depend),
+            ("build_export_depend", 2..) => Some(Self::BuildExport),
+            ("buildtool_export_depend", 2..) => Some(Self::BuildtoolExport),
+            ("exec_depend", 2..) => Some(Self::Exec),
+            ("doc_depend", 2..) => Some(Self::Doc),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Dependency {
+    pub kind: DependencyKind,
+    pub name: String,
+    pub condition: Option<String>,
+    /// Whether the condition holds for the current environment.
+    pub active: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Person {
+    pub name: String,
+    pub email: Option<String>,
+}
+
+/// How a package is built, as declared by its `<export><build_type>`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildType {
+    Catkin,
+    CMake,
+    AmentCMake,
+    AmentPython,
+    Other(String),
+}
+
+impl BuildType {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "catkin" => Self::Catkin,
+            "cmake" => Self::CMake,
+            "ament_cmake" => Self::AmentCMake,
+            "ament_python" => Self::AmentPython,
+            name => Self::Other(name.to_string()),
+        }
+    }
+
+    /// Whether packages of this build type are configured by CMake, and so can export compile
+    /// commands.
+    pub fn uses_cmake(&self) -> bool {
+        matches!(self, Self::Catkin | Self::CMake | Self::AmentCMake)
+    }
+}
+
+impl Display for BuildType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Catkin => write!(f, "catkin"),
+            Self::CMake => write!(f, "cmake"),
+            Self::AmentCMake => write!(f, "ament_cmake"),
+            Self::AmentPython => write!(f, "ament_python"),
+            Self::Other(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl serde::Serialize for BuildType {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BuildType {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|name| Self::from_name(&name))
+    }
+}
+
+/// A `package.xml` of format 1, 2 or 3, as specified in REP 127, REP 140 and REP 149.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageManifest {
+    pub format: u8,
+    pub name: String,
+    pub version: String,
+    pub maintainers: Vec<Person>,
+    pub authors: Vec<Person>,
+    pub licenses: Vec<String>,
+    pub dependencies: Vec<Dependency>,
+    /// The `<export><build_type>` whose condition holds, if any.
+    pub build_type: Option<BuildType>,
+}
+
+impl PackageManifest {
+    /// Parse the manifest in `doc`, evaluating its conditions against `conditions`.
+    pub fn parse(doc: &str, conditions: &Conditions) -> Result<Self> {
+        package_manifest_parser(doc, conditions)
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P, conditions: &Conditions) -> Result<Self> {
+        let doc = std::fs::read_to_string(path)?;
+        Self::parse(&doc, conditions)
+    }
+
+    /// The names of the active dependencies of `kind`.
+    pub fn dependencies(&self, kind: DependencyKind) -> impl Iterator<Item = &str> {
+        self.dependencies
+            .iter()
+            .filter(move |dependency| dependency.kind == kind && dependency.active)
+            .map(|dependency| dependency.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::manifest::BuildType;
+
+    #[test]
+    fn test_build_type() {
+        assert_eq!(BuildType::from_name("ament_cmake"), BuildType::AmentCMake);
+        assert_eq!(
+            BuildType::from_name("meson"),
+            BuildType::Other("meson".to_string())
+        );
+        assert_eq!(
+            BuildType::from_name("ament_python").to_string(),
+            "ament_python"
+        );
+        assert!(BuildType::Catkin.uses_cmake());
+        assert!(!BuildType::AmentPython.uses_cmake());
+        assert!(!BuildType::Other("meson".to_string()).uses_cmake());
+    }
+}
@@ -0,0 +1,149 @@
+use std::{collections::BTreeSet, fmt::Display, path::PathBuf};
+
+use crate::{
+    compile_commands::{self, CompileCommand},
+    paths::{
+        package::Package,
+        package_container::PackageContainer,
+        path::Path,
+        structs::{Build, BuildCompileCommands, Source, Workspace},
+    },
+    workspace_kind::WorkspaceKind,
+};
+
+/// Where to put the merged compile commands database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum MergeTarget {
+    /// The root of the workspace.
+    #[default]
+    Workspace,
+    /// The root of the source space.
+    Source,
+}
+
+/// The result of merging the compile commands of a workspace.
+#[derive(Debug, serde::Serialize)]
+pub struct Merged {
+    pub output: PathBuf,
+    pub databases: usize,
+    pub commands: usize,
+}
+
+impl Display for Merged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "merged {} compile commands from {} databases into {}",
+            self.commands,
+            self.databases,
+            self.output.display()
+        )
+    }
+}
+
+/// Concatenate `databases`, keeping only the first entry for each `(directory, file)` pair.
+pub fn merge_compile_commands<I>(databases: I) -> Vec<CompileCommand>
+where
+    I: IntoIterator<Item = Vec<CompileCommand>>,
+{
+    let mut seen = BTreeSet::new();
+    databases
+        .into_iter()
+        .flatten()
+        .filter(|command| seen.insert((command.directory.clone(), command.file.clone())))
+        .collect()
+}
+
+fn database_paths(workspace: &Workspace) -> Vec<PathBuf> {
+    // catkin_make already writes a single database for the whole workspace.
+    if workspace.kind == WorkspaceKind::CatkinMake {
+        let BuildCompileCommands(database_path) = BuildCompileCommands::from(workspace);
+        return vec![database_path];
+    }
+
+    Build::from(workspace)
+        .get_all_package_paths(&workspace.config)
+        .into_values()
+        .map(|build_package| build_package.compile_commands(&workspace.config))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Merge the compile commands of every built package of `workspace` into a single database at
+/// `target`, which is written unless `dry_run` is set. Unreadable databases are left out.
+pub fn merge_all_compile_commands(
+    workspace: &Workspace,
+    target: MergeTarget,
+    dry_run: bool,
+) -> compile_commands::Result<Merged> {
+    log::debug!("Current workspace: {workspace}");
+
+    let output = match target {
+        MergeTarget::Workspace => workspace.path(),
+        MergeTarget::Source => Source::from(workspace).path(),
+    }
+    .join(&workspace.config.compile_commands_name);
+
+    let databases: Vec<_> = database_paths(workspace)
+        .into_iter()
+        .filter_map(|path| match compile_commands::read(&path) {
+            Ok(commands) => Some(commands),
+            Err(err) => {
+                log::warn!("Leaving out {}: {err}", path.display());
+                None
+            }
+        })
+        .collect();
+    let database_count = databases.len();
+
+    let commands = merge_compile_commands(databases);
+    if !dry_run {
+        compile_commands::write(&output, &commands)?;
+    }
+
+    Ok(Merged {
+        output,
+        databases: database_count,
+        commands: commands.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{compile_commands::CompileCommand, merge::merge_compile_commands};
+
+    fn command(directory: &str, file: &str, command: &str) -> CompileCommand {
+        CompileCommand {
+            directory: directory.into(),
+            file: file.into(),
+            command: Some(command.to_string()),
+            arguments: None,
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_compile_commands() {
+        let first = vec![
+            command("/ws/build/a", "/ws/src/a/a.cpp", "c++ -O2 -c a.cpp"),
+            command("/ws/build/a", "/ws/src/shared/s.cpp", "c++ -O2 -c s.cpp"),
+        ];
+        let second = vec![
+            command("/ws/build/a", "/ws/src/shared/s.cpp", "c++ -O0 -c s.cpp"),
+            command("/ws/build/b", "/ws/src/shared/s.cpp", "c++ -O0 -c s.cpp"),
+            command("/ws/build/b", "/ws/src/b/b.cpp", "c++ -c b.cpp"),
+        ];
+
+        let merged = merge_compile_commands([first, second]);
+
+        assert_eq!(
+            merged,
+            vec![
+                command("/ws/build/a", "/ws/src/a/a.cpp", "c++ -O2 -c a.cpp"),
+                command("/ws/build/a", "/ws/src/shared/s.cpp", "c++ -O2 -c s.cpp"),
+                command("/ws/build/b", "/ws/src/shared/s.cpp", "c++ -O0 -c s.cpp"),
+                command("/ws/build/b", "/ws/src/b/b.cpp", "c++ -c b.cpp"),
+            ]
+        );
+    }
+}
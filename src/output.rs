@@ -0,0 +1,51 @@
+use std::{fmt::Display, io::Write};
+
+use serde::{Deserialize, Serialize};
+
+/// How results are printed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// Human readable text.
+    #[default]
+    Text,
+    /// One JSON document per result, on a single line.
+    Json,
+}
+
+/// Write `line` to stdout. A closed stdout, e.g. when piping into `head`, ends the process
+/// normally, as nobody is left to read the rest.
+fn print_line(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    match writeln!(stdout, "{line}").and_then(|()| stdout.flush()) {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => std::process::exit(0),
+        Err(err) => log::error!("Cannot print output: {err}"),
+    }
+}
+
+/// Print `value` in `format`.
+pub fn print<T: Display + Serialize + ?Sized>(format: Format, value: &T) {
+    match format {
+        Format::Text => print_line(value.to_string().trim_end_matches('\n')),
+        Format::Json => match serde_json::to_string(value) {
+            Ok(json) => print_line(&json),
+            Err(err) => log::error!("Cannot serialize output: {err}"),
+        },
+    }
+}
+
+/// Print `values` in `format`, one per line as text, or as a single JSON array.
+pub fn print_all<T: Display + Serialize>(format: Format, values: &[T]) {
+    match format {
+        Format::Text => {
+            for value in values {
+                print(format, value);
+            }
+        }
+        Format::Json => match serde_json::to_string(values) {
+            Ok(json) => print_line(&json),
+            Err(err) => log::error!("Cannot serialize output: {err}"),
+        },
+    }
+}
@@ -1,8 +1,21 @@
 use std::fmt::Display;
 
-#[derive(Hash, PartialEq, Eq, Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Debug, Deserialize, Serialize)]
 pub struct PackageName(pub(crate) String);
 
+impl PackageName {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self(name.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        let Self(name) = self;
+        name
+    }
+}
+
 impl Display for PackageName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let Self(package) = self;
@@ -1,2 +1,5 @@
+pub mod cmake_cache;
 pub mod cmakelists;
+pub mod condition;
 pub mod package;
+pub mod profile;
@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+/// The entries of a `CMakeCache.txt` file, mapping each variable name to its value.
+pub fn cmake_cache_parser(input: &str) -> BTreeMap<String, String> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#') && !line.starts_with("//"))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            // Entries are written as `NAME:TYPE=VALUE`, but the type is optional.
+            let name = key.split_once(':').map_or(key, |(name, _)| name);
+            (name.to_string(), value.to_string())
+        })
+        .collect()
+}
+
+/// Whether CMake treats `value` as true in an `if()`.
+pub fn is_true(value: &str) -> bool {
+    matches!(
+        value.to_ascii_uppercase().as_str(),
+        "1" | "ON" | "YES" | "TRUE" | "Y"
+    ) || value.parse::<f64>().is_ok_and(|number| number != 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsers::cmake_cache::{cmake_cache_parser, is_true};
+
+    #[test]
+    fn test_cmake_cache() {
+        let cache = "# This is the CMakeCache file.
+# For build in directory: /ws/build/foo
+
+//Build a shared library
+BUILD_SHARED_LIBS:BOOL=ON
+
+//Enable/Disable output of compile commands during generation.
+CMAKE_EXPORT_COMPILE_COMMANDS:BOOL=OFF
+
+CMAKE_INSTALL_PREFIX:PATH=/ws/install/foo
+UNTYPED=value=with=equals
+";
+        let parsed = cmake_cache_parser(cache);
+        assert_eq!(parsed.len(), 4);
+        assert_eq!(parsed["CMAKE_EXPORT_COMPILE_COMMANDS"], "OFF");
+        assert_eq!(parsed["CMAKE_INSTALL_PREFIX"], "/ws/install/foo");
+        assert_eq!(parsed["UNTYPED"], "value=with=equals");
+    }
+
+    #[test]
+    fn test_is_true() {
+        assert!(is_true("ON"));
+        assert!(is_true("yes"));
+        assert!(is_true("2"));
+        assert!(!is_true("OFF"));
+        assert!(!is_true("0"));
+        assert!(!is_true(""));
+        assert!(!is_true("NOTFOUND"));
+    }
+}
@@ -1,25 +1,106 @@
 use nom::{
-    branch::{alt},
-    bytes::complete::{tag, tag_no_case, take_until},
-    character::complete::{
-        alpha1, alphanumeric1, multispace0, not_line_ending,
-    },
-    combinator::{recognize},
-    multi::{many0, many0_count},
-    sequence::{delimited, pair},
+    branch::alt,
+    bytes::complete::{is_not, tag, take_until, take_while, take_while1},
+    character::complete::{alpha1, alphanumeric1, anychar, char, not_line_ending},
+    combinator::{all_consuming, map, recognize, value},
+    multi::{many0, many0_count, many1_count},
+    sequence::{delimited, pair, preceded, tuple},
     IResult,
 };
 
+/// A single argument of a command invocation, as written, without evaluating escape sequences or
+/// variable references.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Argument {
+    /// `[[...]]`, `[=[...]=]` and so on.
+    Bracket(String),
+    /// `"..."`
+    Quoted(String),
+    Unquoted(String),
+}
+
+impl Argument {
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Bracket(value) | Self::Quoted(value) | Self::Unquoted(value) => value,
+        }
+    }
+}
+
+/// A command invocation, e.g. `project(foo)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Command {
+    pub name: String,
+    /// The arguments, including those within nested parentheses.
+    pub arguments: Vec<Argument>,
+}
+
+fn space(input: &str) -> IResult<&str, &str> {
+    take_while1(|c| matches!(c, ' ' | '\t' | '\r'))(input)
+}
+
+/// The contents of a bracket argument, where a newline right after the opening bracket is
+/// ignored.
+fn bracket(input: &str) -> IResult<&str, &str> {
+    let (input, level) = delimited(char('['), many0_count(char('=')), char('['))(input)?;
+    let close = format!("]{}]", "=".repeat(level));
+    let (input, contents) = take_until(close.as_str())(input)?;
+    let (input, _) = tag(close.as_str())(input)?;
+
+    let contents = contents
+        .strip_prefix("\r\n")
+        .or_else(|| contents.strip_prefix('\n'))
+        .unwrap_or(contents);
+    Ok((input, contents))
+}
+
+/// A bracket comment, e.g. `#[[...]]`, or a line comment.
 fn comment_parser(input: &str) -> IResult<&str, ()> {
-    let (input, _) = tag("#")(input)?;
-    let (input, _) = not_line_ending(input)?;
-    let (input, _) = multispace0(input)?;
-    Ok((input, ()))
+    alt((
+        value((), preceded(char('#'), bracket)),
+        value((), pair(char('#'), not_line_ending)),
+    ))(input)
+}
+
+/// Whitespace and comments between arguments and commands.
+fn separation(input: &str) -> IResult<&str, ()> {
+    alt((value((), space), value((), char('\n')), comment_parser))(input)
+}
+
+fn escape_sequence(input: &str) -> IResult<&str, &str> {
+    recognize(pair(char('\\'), anychar))(input)
+}
+
+fn quoted(input: &str) -> IResult<&str, &str> {
+    delimited(
+        char('"'),
+        recognize(many0_count(alt((escape_sequence, is_not("\\\""))))),
+        char('"'),
+    )(input)
+}
+
+fn unquoted(input: &str) -> IResult<&str, &str> {
+    recognize(many1_count(alt((
+        escape_sequence,
+        is_not(" \t\r\n()#\"\\"),
+    ))))(input)
 }
 
-fn comment_parser0(input: &str) -> IResult<&str, ()> {
-    let (input, _) = many0(comment_parser)(input)?;
-    Ok((input, ()))
+fn argument(input: &str) -> IResult<&str, Argument> {
+    alt((
+        map(bracket, |value| Argument::Bracket(value.to_string())),
+        map(quoted, |value| Argument::Quoted(value.to_string())),
+        map(unquoted, |value| Argument::Unquoted(value.to_string())),
+    ))(input)
+}
+
+fn arguments(input: &str) -> IResult<&str, Vec<Argument>> {
+    let (input, groups) = many0(alt((
+        map(separation, |()| vec![]),
+        map(argument, |argument| vec![argument]),
+        delimited(char('('), arguments, char(')')),
+    )))(input)?;
+    Ok((input, groups.into_iter().flatten().collect()))
 }
 
 pub fn identifier(input: &str) -> IResult<&str, &str> {
@@ -29,38 +110,74 @@ pub fn identifier(input: &str) -> IResult<&str, &str> {
     ))(input)
 }
 
-fn project_name_parser(input: &str) -> nom::IResult<&str, &str> {
-    let (input, _) = tag_no_case("project")(input)?;
-    let (input, _) = multispace0(input)?;
-
-    let (input, _) = comment_parser0(input)?;
-    let (input, _) = delimited(multispace0, tag("("), multispace0)(input)?;
-
-    let (input, _) = comment_parser0(input)?;
-    let (input, project_name) = delimited(multispace0, identifier, multispace0)(input)?;
+fn command_parser(input: &str) -> IResult<&str, Command> {
+    let (input, (name, _, arguments)) = tuple((
+        identifier,
+        take_while(|c| c == ' ' || c == '\t'),
+        delimited(char('('), arguments, char(')')),
+    ))(input)?;
+    Ok((
+        input,
+        Command {
+            name: name.to_string(),
+            arguments,
+        },
+    ))
+}
 
-    let (input, _) = comment_parser0(input)?;
-    let (input, _) = delimited(multispace0, tag(")"), multispace0)(input)?;
-    Ok((input, project_name))
+/// A command invocation, or whitespace and comments between them.
+fn file_element(input: &str) -> IResult<&str, Option<Command>> {
+    alt((map(command_parser, Some), map(separation, |()| None)))(input)
 }
 
-fn internal_cmakelists_name_parser(input: &str) -> IResult<&str, &str> {
-    let (input, _) = take_until("project")(input)?;
-    project_name_parser(input)
+/// Every command invocation in a CMake file.
+pub fn commands_parser(input: &str) -> Option<Vec<Command>> {
+    let (_, elements) = all_consuming(many0(file_element))(input).ok()?;
+    Some(elements.into_iter().flatten().collect())
 }
 
+/// The name given to the first `project()` outside of function and macro definitions. Only the
+/// file up to that command has to be valid CMake.
 pub fn cmakelists_name_parser(input: &str) -> Option<String> {
-    let (_, name) = internal_cmakelists_name_parser(input).ok()?;
+    let mut input = input;
+    let mut definitions = 0usize;
+
+    while !input.is_empty() {
+        let (rest, element) = file_element(input).ok()?;
+        input = rest;
+
+        let Some(command) = element else {
+            continue;
+        };
+        match command.name.to_ascii_lowercase().as_str() {
+            "function" | "macro" => definitions += 1,
+            "endfunction" | "endmacro" => definitions = definitions.saturating_sub(1),
+            "project" if definitions == 0 => {
+                return command
+                    .arguments
+                    .first()
+                    .map(|argument| argument.value().to_string());
+            }
+            _ => {}
+        }
+    }
 
-    Some(name.to_string())
+    None
 }
 
 #[cfg(test)]
 mod tests {
     use crate::parsers::cmakelists::{
-        comment_parser, internal_cmakelists_name_parser, project_name_parser,
+        cmakelists_name_parser, command_parser, commands_parser, comment_parser, Argument, Command,
     };
 
+    fn project(name: &str) -> Command {
+        Command {
+            name: "project".to_string(),
+            arguments: vec![Argument::Unquoted(name.to_string())],
+        }
+    }
+
     #[test]
     fn test_cmakelists() {
         let cmakelists = "cmake_minimum_required(VERSION 3.10.0)
@@ -183,30 +300,33 @@ install(
   DESTINATION ${CATKIN_PACKAGE_SHARE_DESTINATION}
 )
 ";
-        if let Ok((_, project_name)) = internal_cmakelists_name_parser(cmakelists) {
-            assert_eq!(project_name, "ouster_ros");
-        } else {
-            assert_eq!(1, 2);
-        }
+        assert_eq!(
+            cmakelists_name_parser(cmakelists),
+            Some("ouster_ros".to_string())
+        );
+        assert_eq!(
+            commands_parser(cmakelists).map(|commands| commands.len()),
+            Some(37)
+        );
     }
 
     #[test]
     fn test_project_name() {
         let cmake_example = "project(example_name)";
-        let parsed = project_name_parser(cmake_example);
-        assert_eq!(parsed, Ok(("", "example_name")));
+        let parsed = command_parser(cmake_example);
+        assert_eq!(parsed, Ok(("", project("example_name"))));
         let cmake_morespaced = "project   ( 
             example_ADS3Cname )";
-        let parsed = project_name_parser(cmake_morespaced);
-        assert_eq!(parsed, Ok(("", "example_ADS3Cname")));
+        let parsed = command_parser(cmake_morespaced);
+        assert_eq!(parsed, Ok(("", project("example_ADS3Cname"))));
         let cmake_spaced_and_commented = "project (
             # this is an important comment
             # before the project 
             # name
             example_name 
             )";
-        let parsed = project_name_parser(cmake_spaced_and_commented);
-        assert_eq!(parsed, Ok(("", "example_name")));
+        let parsed = command_parser(cmake_spaced_and_commented);
+        assert_eq!(parsed, Ok(("", project("example_name"))));
     }
 
     #[test]
@@ -217,7 +337,64 @@ install(
         let comment = "# this is a comment
 
             this is the rest";
-        assert_eq!(comment_parser(comment), Ok(("this is the rest", ())));
-        // assert_eq!(pinline_comment(comment), Ok(()))
+        assert_eq!(
+            comment_parser(comment),
+            Ok(("\n\n            this is the rest", ()))
+        );
+
+        let comment = "#[==[ a bracket comment ]]
+            spanning lines ]==] project(foo)";
+        assert_eq!(comment_parser(comment), Ok((" project(foo)", ())));
+    }
+
+    #[test]
+    fn test_arguments() {
+        let command = "message(STATUS \"quoted (\\\") # not a comment\" [=[bracket ]] argument]=] (nested ${PROJECT_NAME}))";
+        assert_eq!(
+            command_parser(command),
+            Ok((
+                "",
+                Command {
+                    name: "message".to_string(),
+                    arguments: vec![
+                        Argument::Unquoted("STATUS".to_string()),
+                        Argument::Quoted("quoted (\\\") # not a comment".to_string()),
+                        Argument::Bracket("bracket ]] argument".to_string()),
+                        Argument::Unquoted("nested".to_string()),
+                        Argument::Unquoted("${PROJECT_NAME}".to_string()),
+                    ]
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn test_first_project() {
+        let cmakelists = "cmake_minimum_required(VERSION 3.10)
+# this project uses project(wrong) in a comment
+#[[ project(wrong) in a bracket comment ]]
+set(NAME \"project(wrong)\" ${PROJECT_NAME})
+catkin_project_foo()
+function(define_project)
+  project(wrong)
+endfunction()
+PROJECT (right VERSION 1.0 LANGUAGES CXX)
+project(later)
+";
+        assert_eq!(
+            cmakelists_name_parser(cmakelists),
+            Some("right".to_string())
+        );
+
+        assert_eq!(
+            cmakelists_name_parser("project(\"quoted\")"),
+            Some("quoted".to_string())
+        );
+        assert_eq!(
+            cmakelists_name_parser("project(foo)\nunbalanced(("),
+            Some("foo".to_string())
+        );
+        assert_eq!(cmakelists_name_parser("unbalanced((\nproject(foo)"), None);
+        assert_eq!(cmakelists_name_parser("# project(foo)"), None);
     }
 }
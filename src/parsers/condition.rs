@@ -0,0 +1,192 @@
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::{char, multispace0},
+    combinator::{all_consuming, map, opt, recognize},
+    multi::many0,
+    sequence::{delimited, pair, preceded, tuple},
+    IResult,
+};
+
+/// An operand of a `condition` attribute, either a `$VARIABLE` or a literal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+    Variable(String),
+    Literal(String),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    GreaterOrEqual,
+    Greater,
+    LessOrEqual,
+    Less,
+}
+
+/// A parsed `condition` attribute of a `package.xml`, as specified in REP 149.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Value(Value),
+    Compare(Value, Operator, Value),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+impl Condition {
+    /// Evaluate the condition, looking up variables with `variable`. Like catkin_pkg, values are
+    /// compared as strings, and a lone value is true if it is not empty.
+    pub fn evaluate<F: Fn(&str) -> Option<String>>(&self, variable: &F) -> bool {
+        let resolve = |value: &Value| match value {
+            Value::Variable(name) => variable(name).unwrap_or_default(),
+            Value::Literal(literal) => literal.clone(),
+        };
+
+        match self {
+            Self::Value(value) => !resolve(value).is_empty(),
+            Self::Compare(left, operator, right) => {
+                let (left, right) = (resolve(left), resolve(right));
+                match operator {
+                    Operator::Equal => left == right,
+                    Operator::NotEqual => left != right,
+                    Operator::GreaterOrEqual => left >= right,
+                    Operator::Greater => left > right,
+                    Operator::LessOrEqual => left <= right,
+                    Operator::Less => left < right,
+                }
+            }
+            Self::And(left, right) => left.evaluate(variable) && right.evaluate(variable),
+            Self::Or(left, right) => left.evaluate(variable) || right.evaluate(variable),
+        }
+    }
+}
+
+fn is_value_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+fn quoted(input: &str) -> IResult<&str, &str> {
+    alt((
+        delimited(char('"'), take_while(|c| c != '"'), char('"')),
+        delimited(char('\''), take_while(|c| c != '\''), char('\'')),
+    ))(input)
+}
+
+fn value(input: &str) -> IResult<&str, Value> {
+    let variable = map(
+        preceded(char('$'), take_while1(is_value_char)),
+        |name: &str| Value::Variable(name.to_string()),
+    );
+    let literal = map(
+        alt((quoted, recognize(take_while1(is_value_char)))),
+        |literal: &str| Value::Literal(literal.to_string()),
+    );
+    delimited(multispace0, alt((variable, literal)), multispace0)(input)
+}
+
+fn operator(input: &str) -> IResult<&str, Operator> {
+    alt((
+        map(tag("=="), |_| Operator::Equal),
+        map(tag("!="), |_| Operator::NotEqual),
+        map(tag(">="), |_| Operator::GreaterOrEqual),
+        map(tag(">"), |_| Operator::Greater),
+        map(tag("<="), |_| Operator::LessOrEqual),
+        map(tag("<"), |_| Operator::Less),
+    ))(input)
+}
+
+fn comparison(input: &str) -> IResult<&str, Condition> {
+    let (input, left) = value(input)?;
+    let (input, right) = opt(pair(operator, value))(input)?;
+    Ok((
+        input,
+        match right {
+            Some((operator, right)) => Condition::Compare(left, operator, right),
+            None => Condition::Value(left),
+        },
+    ))
+}
+
+fn primary(input: &str) -> IResult<&str, Condition> {
+    alt((
+        delimited(
+            tuple((multispace0, char('('))),
+            or_condition,
+            tuple((char(')'), multispace0)),
+        ),
+        comparison,
+    ))(input)
+}
+
+fn and_condition(input: &str) -> IResult<&str, Condition> {
+    let (input, first) = primary(input)?;
+    let (input, rest) = many0(preceded(tag("and"), primary))(input)?;
+    Ok((
+        input,
+        rest.into_iter().fold(first, |left, right| {
+            Condition::And(Box::new(left), Box::new(right))
+        }),
+    ))
+}
+
+fn or_condition(input: &str) -> IResult<&str, Condition> {
+    let (input, first) = and_condition(input)?;
+    let (input, rest) = many0(preceded(tag("or"), and_condition))(input)?;
+    Ok((
+        input,
+        rest.into_iter().fold(first, |left, right| {
+            Condition::Or(Box::new(left), Box::new(right))
+        }),
+    ))
+}
+
+pub fn condition_parser(input: &str) -> Option<Condition> {
+    let (_, condition) = all_consuming(or_condition)(input).ok()?;
+    Some(condition)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parsers::condition::{condition_parser, Condition, Operator, Value};
+
+    fn ros(name: &str) -> Option<String> {
+        match name {
+            "ROS_VERSION" => Some("2".to_string()),
+            "ROS_DISTRO" => Some("humble".to_string()),
+            _ => None,
+        }
+    }
+
+    fn evaluate(condition: &str) -> bool {
+        condition_parser(condition).unwrap().evaluate(&ros)
+    }
+
+    #[test]
+    fn test_condition_parser() {
+        assert_eq!(
+            condition_parser("$ROS_VERSION == 2"),
+            Some(Condition::Compare(
+                Value::Variable("ROS_VERSION".to_string()),
+                Operator::Equal,
+                Value::Literal("2".to_string())
+            ))
+        );
+        assert!(condition_parser("$ROS_VERSION ==").is_none());
+        assert!(condition_parser("($ROS_VERSION == 2").is_none());
+    }
+
+    #[test]
+    fn test_condition_evaluate() {
+        assert!(evaluate("$ROS_VERSION == 2"));
+        assert!(!evaluate("$ROS_VERSION == 1"));
+        assert!(evaluate("$ROS_DISTRO != 'noetic'"));
+        assert!(evaluate("$ROS_VERSION == 1 or $ROS_DISTRO == humble"));
+        assert!(!evaluate(
+            "$ROS_VERSION == 2 and ($ROS_DISTRO == foxy or $ROS_DISTRO == galactic)"
+        ));
+        assert!(evaluate("$ROS_DISTRO >= galactic"));
+        assert!(!evaluate("$UNDEFINED"));
+        assert!(evaluate("$UNDEFINED == ''"));
+    }
+}
@@ -1,56 +1,175 @@
-use quick_xml::events::Event;
+use quick_xml::events::{BytesStart, Event};
 
-pub fn package_name_parser(input: &str) -> Option<String> {
+use crate::manifest::{
+    BuildType, Conditions, Dependency, DependencyKind, Error, PackageManifest, Person, Result,
+};
+
+/// An element of interest whose text is still being read.
+struct Element {
+    tag: String,
+    /// The number of elements enclosing this element.
+    depth: usize,
+    email: Option<String>,
+    condition: Option<String>,
+    text: String,
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attribute in e.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_ref() == name {
+            return Ok(Some(attribute.unescape_value()?.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+fn add_element(
+    manifest: &mut PackageManifest,
+    element: Element,
+    conditions: &Conditions,
+) -> Result<()> {
+    let Element {
+        tag,
+        depth,
+        email,
+        condition,
+        text,
+    } = element;
+    let text = text.trim().to_string();
+
+    if depth == 2 {
+        if tag == "build_type"
+            && manifest.build_type.is_none()
+            && conditions.evaluate(condition.as_deref())?
+        {
+            manifest.build_type = Some(BuildType::from_name(&text));
+        }
+        return Ok(());
+    }
+
+    match tag.as_str() {
+        "name" => manifest.name = text,
+        "version" => manifest.version = text,
+        "maintainer" => manifest.maintainers.push(Person { name: text, email }),
+        "author" => manifest.authors.push(Person { name: text, email }),
+        "license" => manifest.licenses.push(text),
+        tag => {
+            if let Some(kind) = DependencyKind::from_tag(tag, manifest.format) {
+                let active = conditions.evaluate(condition.as_deref())?;
+                manifest.dependencies.push(Dependency {
+                    kind,
+                    name: text,
+                    condition,
+                    active,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parse a `package.xml`, evaluating its `condition` attributes against `conditions`. Only the
+/// direct children of `<package>` and `<export>` are considered, so e.g. a `<name>` within
+/// `<export>` is never taken for the package name.
+pub fn package_manifest_parser(input: &str, conditions: &Conditions) -> Result<PackageManifest> {
     let mut reader = quick_xml::reader::Reader::from_str(input);
 
     reader.trim_text(true);
 
-    let mut within_name_tag = false;
-    let mut package_name: Option<String> = None;
+    let mut manifest = PackageManifest {
+        format: 1,
+        name: String::new(),
+        version: String::new(),
+        maintainers: vec![],
+        authors: vec![],
+        licenses: vec![],
+        dependencies: vec![],
+        build_type: None,
+    };
+    let mut elements: Vec<Vec<u8>> = Vec::new();
+    let mut element: Option<Element> = None;
+    let mut found_package = false;
 
     loop {
-        match reader.read_event() {
-            Err(e) => {
-                log::warn!("Error at position {}: {:?}", reader.buffer_position(), e);
-            }
-            Ok(Event::Eof) => {
-                log::debug!("Finished parsing file");
-                break;
-            }
-            Ok(Event::Start(e)) => {
-                if e.name().as_ref() == b"name" {
-                    within_name_tag = true;
+        match reader.read_event()? {
+            Event::Eof => break,
+            Event::Start(e) => {
+                let tag = e.name().as_ref().to_vec();
+                if elements.is_empty() {
+                    if tag != b"package" {
+                        return Err(Error::MissingElement("package"));
+                    }
+                    found_package = true;
+                    if let Some(format) = attribute(&e, b"format")? {
+                        manifest.format = match format.parse() {
+                            Ok(format @ 1..=3) => format,
+                            _ => return Err(Error::UnsupportedFormat(format)),
+                        };
+                    }
+                } else if elements.len() == 1 || (elements.len() == 2 && elements[1] == b"export") {
+                    element = Some(Element {
+                        tag: String::from_utf8_lossy(&tag).to_string(),
+                        depth: elements.len(),
+                        email: attribute(&e, b"email")?,
+                        condition: attribute(&e, b"condition")?,
+                        text: String::new(),
+                    });
                 }
+                elements.push(tag);
             }
-            Ok(Event::Text(e)) => {
-                if within_name_tag {
-                    match e.unescape() {
-                        Ok(text) => {
-                            package_name = Some(text.to_string());
-                        }
-                        Err(_) => {
-                            package_name = None;
-                        }
-                    }
+            Event::Text(e) => {
+                if let Some(element) = element
+                    .as_mut()
+                    .filter(|element| element.depth + 1 == elements.len())
+                {
+                    element.text.push_str(&e.unescape()?);
                 }
             }
-            Ok(Event::End(e)) => {
-                if e.name().as_ref() == b"name" {
-                    break;
+            Event::End(_) => {
+                elements.pop();
+                if element
+                    .as_ref()
+                    .is_some_and(|element| element.depth == elements.len())
+                {
+                    if let Some(element) = element.take() {
+                        add_element(&mut manifest, element, conditions)?;
+                    }
                 }
             }
-            Ok(_) => {}
+            _ => {}
         }
     }
 
-    package_name
+    if !found_package {
+        return Err(Error::MissingElement("package"));
+    }
+    if manifest.name.is_empty() {
+        return Err(Error::MissingElement("name"));
+    }
+    if manifest.version.is_empty() {
+        return Err(Error::MissingElement("version"));
+    }
+    Ok(manifest)
 }
 
 #[cfg(test)]
 mod tests {
     use quick_xml::{events::Event, Reader};
 
-    use crate::parsers::package::package_name_parser;
+    use crate::{
+        manifest::{BuildType, Conditions, DependencyKind, Error, Person},
+        parsers::package::package_manifest_parser,
+    };
+
+    fn ros2() -> Conditions {
+        Conditions(
+            [("ROS_VERSION", "2"), ("ROS_DISTRO", "humble")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
 
     #[test]
     fn test_package() {
@@ -91,8 +210,107 @@ mod tests {
   </export>
 </package>
             ";
-        let package_name = package_name_parser(pack);
-        assert_eq!(package_name, Some("ouster_ros".to_string()));
+        let manifest = package_manifest_parser(pack, &Conditions::default()).unwrap();
+        assert_eq!(manifest.format, 3);
+        assert_eq!(manifest.name, "ouster_ros");
+        assert_eq!(manifest.version, "0.7.2");
+        assert_eq!(
+            manifest.maintainers,
+            vec![Person {
+                name: "ouster developers".to_string(),
+                email: Some("oss@ouster.io".to_string())
+            }]
+        );
+        assert_eq!(manifest.licenses, vec!["BSD"]);
+        assert_eq!(
+            manifest
+                .dependencies(DependencyKind::Buildtool)
+                .collect::<Vec<_>>(),
+            vec!["catkin"]
+        );
+        assert_eq!(manifest.dependencies(DependencyKind::Depend).count(), 7);
+        assert_eq!(manifest.dependencies(DependencyKind::Exec).count(), 6);
+        assert_eq!(manifest.build_type, None);
+    }
+
+    #[test]
+    fn test_nested_name() {
+        let pack = "
+<?xml version=\"1.0\"?>
+<package>
+  <export>
+    <name>not_the_package</name>
+  </export>
+  <name>demo</name>
+  <version>1.0.0</version>
+  <run_depend>roscpp</run_depend>
+  <exec_depend>ignored_in_format_1</exec_depend>
+</package>
+            ";
+        let manifest = package_manifest_parser(pack, &Conditions::default()).unwrap();
+        assert_eq!(manifest.format, 1);
+        assert_eq!(manifest.name, "demo");
+        assert_eq!(
+            manifest
+                .dependencies(DependencyKind::Run)
+                .collect::<Vec<_>>(),
+            vec!["roscpp"]
+        );
+        assert!(manifest.dependencies(DependencyKind::Exec).next().is_none());
+    }
+
+    #[test]
+    fn test_conditions() {
+        let pack = "
+<?xml version=\"1.0\"?>
+<package format=\"3\">
+  <name>demo_py</name>
+  <version>0.1.0</version>
+  <build_type>not_exported</build_type>
+  <depend condition=\"$ROS_VERSION == 1\">rospy</depend>
+  <depend condition=\"$ROS_VERSION == 2\">rclpy</depend>
+  <export>
+    <build_type condition=\"$ROS_VERSION == 1\">catkin</build_type>
+    <build_type condition=\"$ROS_VERSION == 2\">ament_python</build_type>
+  </export>
+</package>
+            ";
+        let manifest = package_manifest_parser(pack, &ros2()).unwrap();
+        assert_eq!(manifest.build_type, Some(BuildType::AmentPython));
+        assert_eq!(
+            manifest
+                .dependencies(DependencyKind::Depend)
+                .collect::<Vec<_>>(),
+            vec!["rclpy"]
+        );
+        assert_eq!(manifest.dependencies.len(), 2);
+    }
+
+    #[test]
+    fn test_invalid_manifest() {
+        let conditions = Conditions::default();
+        assert!(matches!(
+            package_manifest_parser(
+                "<package format=\"2\"><version>1.0.0</version></package>",
+                &conditions
+            ),
+            Err(Error::MissingElement("name"))
+        ));
+        assert!(matches!(
+            package_manifest_parser("<package format=\"4\"></package>", &conditions),
+            Err(Error::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            package_manifest_parser("<package><name>foo</package>", &conditions),
+            Err(Error::Xml(_))
+        ));
+        assert!(matches!(
+            package_manifest_parser(
+                "<package format=\"3\"><name>foo</name><version>1.0.0</version><depend condition=\"$ROS_VERSION ==\">bar</depend></package>",
+                &conditions
+            ),
+            Err(Error::InvalidCondition(_))
+        ));
     }
 
     #[test]
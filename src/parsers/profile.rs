@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// The subset of `.catkin_tools/profiles/profiles.yaml` symlinkccc cares about.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct ProfilesConfig {
+    #[serde(default)]
+    pub active: Option<String>,
+}
+
+/// The subset of `.catkin_tools/profiles/<profile>/config.yaml` symlinkccc cares about.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct ProfileConfig {
+    #[serde(default)]
+    pub build_space: Option<PathBuf>,
+    #[serde(default)]
+    pub source_space: Option<PathBuf>,
+    #[serde(default)]
+    pub cmake_args: Option<Vec<String>>,
+}
+
+fn parse_or_default<T: for<'de> Deserialize<'de> + Default>(input: &str) -> serde_yaml::Result<T> {
+    // catkin writes empty files for freshly created profiles, which is not a valid YAML mapping.
+    if input.trim().is_empty() {
+        return Ok(T::default());
+    }
+    serde_yaml::from_str(input)
+}
+
+pub fn profiles_parser(input: &str) -> serde_yaml::Result<ProfilesConfig> {
+    parse_or_default(input)
+}
+
+pub fn profile_config_parser(input: &str) -> serde_yaml::Result<ProfileConfig> {
+    parse_or_default(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::parsers::profile::{profile_config_parser, profiles_parser};
+
+    #[test]
+    fn test_profiles() {
+        let profiles = "active: release\n";
+        let parsed = profiles_parser(profiles).unwrap();
+        assert_eq!(parsed.active, Some("release".to_string()));
+
+        let parsed = profiles_parser("").unwrap();
+        assert_eq!(parsed.active, None);
+    }
+
+    #[test]
+    fn test_profile_config() {
+        let config = "authors: []
+blacklist: []
+build_space: build_release
+catkin_make_args: []
+cmake_args:
+- -DCMAKE_BUILD_TYPE=Release
+- -DCMAKE_EXPORT_COMPILE_COMMANDS=ON
+devel_layout: linked
+devel_space: devel_release
+extend_path: null
+install: false
+log_space: logs_release
+source_space: ws_src
+use_env_cache: false
+whitelist: []
+";
+        let parsed = profile_config_parser(config).unwrap();
+        assert_eq!(parsed.build_space, Some(PathBuf::from("build_release")));
+        assert_eq!(parsed.source_space, Some(PathBuf::from("ws_src")));
+        assert_eq!(
+            parsed.cmake_args,
+            Some(vec![
+                "-DCMAKE_BUILD_TYPE=Release".to_string(),
+                "-DCMAKE_EXPORT_COMPILE_COMMANDS=ON".to_string()
+            ])
+        );
+
+        let parsed = profile_config_parser("install: true\n").unwrap();
+        assert_eq!(parsed.build_space, None);
+        assert_eq!(parsed.source_space, None);
+    }
+}
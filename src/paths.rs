@@ -5,3 +5,8 @@ pub mod package_container;
 pub mod path;
 pub mod source;
 pub mod structs;
+
+/// The modification time of the file at `path`, or none if it does not exist.
+pub fn modified(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use crate::package_name::PackageName;
+use crate::{config::Config, package_name::PackageName};
 
 use super::{
     package_container::PackageContainer,
@@ -16,21 +16,21 @@ fn path_to_string<P: AsRef<std::path::Path>>(path: P) -> Option<String> {
 impl PackageContainer for Build {
     type PackageType = BuildPackage;
 
-    fn get_all_package_paths(&self) -> HashMap<PackageName, Self::PackageType> {
+    fn get_all_package_paths(&self, config: &Config) -> BTreeMap<PackageName, Self::PackageType> {
         log::info!("Getting all built packages in the build folder");
         let Self(build_path) = self;
 
         log::debug!("Got build path: {}", build_path.display());
 
         std::fs::read_dir(build_path).map_or_else(
-            |_| HashMap::new(),
+            |_| BTreeMap::new(),
             |paths| {
                 paths
                     .filter_map(std::result::Result::ok)
                     .map(|e| e.path())
                     .filter(|p| p.exists() && p.is_dir())
                     .filter_map(path_to_string)
-                    .filter(|s| !crate::config::BUILD_IGNORE_DIRS.contains(&s.as_str()))
+                    .filter(|s| !config.build_ignore_dirs.contains(s))
                     .map(|s| (PackageName(s.clone()), BuildPackage(build_path.join(s))))
                     .collect()
             },
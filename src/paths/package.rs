@@ -1,9 +1,8 @@
-pub trait Package: super::path::Path {
-    fn compile_commands_filename() -> &'static str {
-        crate::config::COMPILE_COMMANDS_NAME
-    }
+use crate::config::Config;
 
-    fn compile_commands(&self) -> std::path::PathBuf {
-        self.path().join(Self::compile_commands_filename())
+pub trait Package: super::path::Path {
+    /// The compile commands database of the package, named as configured in `config`.
+    fn compile_commands(&self, config: &Config) -> std::path::PathBuf {
+        self.path().join(&config.compile_commands_name)
     }
 }
@@ -1,9 +1,9 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
-use crate::package_name::PackageName;
+use crate::{config::Config, package_name::PackageName};
 
 pub trait PackageContainer {
     type PackageType;
 
-    fn get_all_package_paths(&self) -> HashMap<PackageName, Self::PackageType>;
+    fn get_all_package_paths(&self, config: &Config) -> BTreeMap<PackageName, Self::PackageType>;
 }
@@ -1,12 +1,20 @@
-use std::collections::HashMap;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
 
-use walkdir::{DirEntry, WalkDir};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
 
 use crate::{
+    cache::{CachedChild, CachedFolder, CachedPackage, DiscoveryCache, Stamp},
+    config::Config,
+    manifest::{BuildType, Conditions, PackageManifest},
     package_name::PackageName,
-    parsers::{cmakelists::cmakelists_name_parser, package::package_name_parser},
+    parsers::cmakelists::cmakelists_name_parser,
     paths::{
         origin_file::OriginFile,
+        path::Path as _,
         structs::{Source, SourcePackage, SourcePackageCMakeLists, SourcePackageXML},
     },
 };
@@ -38,15 +46,48 @@ fn get_file_package_name(
     )
 }
 
-fn get_package_name(source: &SourcePackage) -> Option<PackageName> {
-    log::info!("Getting package name for potential package: {source:?}");
+impl SourcePackage {
+    pub fn manifest(&self) -> crate::manifest::Result<PackageManifest> {
+        let SourcePackageXML(xml_path) = SourcePackageXML::from(self);
+        PackageManifest::read(xml_path, &Conditions::from_env())
+    }
 
-    let xml = SourcePackageXML::from(source);
-    let xml = xml.canonicalize().ok()?;
+    /// The build type of the package with `manifest`. Packages which declare none are catkin
+    /// packages, unless they only have a `setup.py`.
+    fn classify(&self, manifest: &PackageManifest) -> BuildType {
+        manifest.build_type.clone().unwrap_or_else(|| {
+            if !SourcePackageCMakeLists::from(self).exists()
+                && self.path().join("setup.py").is_file()
+            {
+                BuildType::Other("python".to_string())
+            } else {
+                BuildType::Catkin
+            }
+        })
+    }
 
-    let cmakelists = SourcePackageCMakeLists::from(source);
-    let cmakelists = cmakelists.canonicalize().ok()?;
+    /// The build type of the package, or none if its manifest is invalid. Discovered packages
+    /// know their build type, others read their manifest.
+    pub fn build_type(&self) -> Option<BuildType> {
+        if let Some(build_type) = &self.build_type {
+            return Some(build_type.clone());
+        }
+        match self.manifest() {
+            Ok(manifest) => Some(self.classify(&manifest)),
+            Err(err) => {
+                log::warn!("Cannot read the manifest of {self}: {err}");
+                None
+            }
+        }
+    }
+}
+
+/// The name and build type of the package candidate `source`, or none if it is not a valid
+/// package.
+fn get_package(source: &SourcePackage) -> Option<(PackageName, BuildType)> {
+    log::info!("Getting package name for potential package: {source:?}");
 
+    let xml = SourcePackageXML::from(source);
     if !xml.exists() {
         log::info!("Assuming {source} is not a package as it has no package.xml file");
         log::debug!("Can't find package.xml file: {xml}");
@@ -54,62 +95,471 @@ fn get_package_name(source: &SourcePackage) -> Option<PackageName> {
         return None;
     }
 
+    let manifest = match source.manifest() {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            log::warn!("Ignoring invalid manifest {xml}: {err}");
+            return None;
+        }
+    };
+    let xml = PackageName(manifest.name.clone());
+    log::debug!("Package name from package.xml: {xml}");
+
+    let build_type = source.classify(&manifest);
+    if !build_type.uses_cmake() {
+        log::info!("Found package name: {xml}, a {build_type} package without compile commands");
+        return Some((xml, build_type));
+    }
+
+    let cmakelists = SourcePackageCMakeLists::from(source);
     if !cmakelists.exists() {
         log::info!("Assuming {source} is not a package as it has no CMakeLists.txt file");
         log::debug!("Can't find CMakeLists.txt file: {cmakelists}");
         return None;
     }
-    let SourcePackageXML(xml_path) = xml;
-    let xml = get_file_package_name(&xml_path, package_name_parser)?;
-    log::debug!("Package name from package.xml: {xml}");
-
-    let SourcePackageCMakeLists(cmakelists_path) = cmakelists;
+    let SourcePackageCMakeLists(cmakelists_path) = cmakelists.canonicalize().ok()?;
     let cmakelists = get_file_package_name(&cmakelists_path, cmakelists_name_parser)?;
     log::debug!("Package name from CMakeLists.txt: {cmakelists}");
 
     if xml == cmakelists {
         log::info!("Found package name: {xml}");
-        Some(xml)
+        Some((xml, build_type))
     } else {
         log::warn!("Package name from package.xml and CMakeLists.txt are different!");
         None
     }
 }
 
-fn get_package_name_from_entry(entry: &walkdir::DirEntry) -> Option<(PackageName, SourcePackage)> {
-    let path = entry.path();
-    if !path.exists() || !path.is_dir() {
-        log::info!("Path is not a valid package {}", path.display());
-        return None;
+/// The package name and build type of `package`, reusing the cached ones if the files they
+/// depend on did not change.
+fn cached_package(package: &SourcePackage, cache: &DiscoveryCache) -> CachedPackage {
+    let package_xml = Stamp::of(&SourcePackageXML::from(package).path());
+    let cmakelists = Stamp::of(&SourcePackageCMakeLists::from(package).path());
+    let setup_py = Stamp::of(&package.path().join("setup.py"));
+
+    if let Some(cached) = cache.packages.get(&package.path()) {
+        if cached.package_xml == package_xml
+            && cached.cmakelists == cmakelists
+            && cached.setup_py == setup_py
+        {
+            log::debug!("Using cached package name for {package}");
+            return cached.clone();
+        }
     }
 
-    let package = SourcePackage(path.to_path_buf());
-    let package_name = get_package_name(&package)?;
+    let (package_name, build_type) = get_package(package).unzip();
+    CachedPackage {
+        package_xml,
+        cmakelists,
+        setup_py,
+        package_name,
+        build_type,
+    }
+}
 
-    Some((package_name, package))
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
 }
 
-fn is_hidden(entry: &DirEntry) -> bool {
-    entry
-        .file_name()
-        .to_str()
-        .map_or(false, |s| s.starts_with('.'))
+/// List the folder at `path`, or reuse the cached listing if the folder did not change.
+fn list_folder(path: &Path, cache: &DiscoveryCache) -> std::io::Result<CachedFolder> {
+    let stamp = Stamp {
+        modified: std::fs::metadata(path)?.modified()?,
+        size: 0,
+    };
+    if let Some(cached) = cache.folders.get(path) {
+        if cached.stamp == stamp {
+            return Ok(cached.clone());
+        }
+    }
+
+    let mut listing = CachedFolder {
+        stamp,
+        ignore_marker: None,
+        package_xml: false,
+        children: vec![],
+    };
+    for entry in std::fs::read_dir(path)?.filter_map(std::result::Result::ok) {
+        let Some(name) = entry.file_name().to_str().map(ToString::to_string) else {
+            continue;
+        };
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if name == "package.xml" {
+            listing.package_xml = true;
+        } else if let Some(marker) = crate::config::IGNORE_MARKER_NAMES
+            .into_iter()
+            .find(|marker| *marker == name)
+        {
+            listing.ignore_marker = Some(marker.to_string());
+        } else if !is_hidden(&name) && (file_type.is_dir() || file_type.is_symlink()) {
+            listing.children.push(CachedChild {
+                name,
+                symlink: file_type.is_symlink(),
+            });
+        }
+    }
+    listing.children.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(listing)
 }
 
-impl PackageContainer for Source {
-    type PackageType = SourcePackage;
+fn ignore_patterns(patterns: &[String]) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        match Glob::new(pattern) {
+            Ok(glob) => {
+                builder.add(glob);
+            }
+            Err(err) => log::warn!("Ignoring invalid ignore pattern '{pattern}': {err}"),
+        }
+    }
+    builder.build().unwrap_or_else(|err| {
+        log::warn!("Cannot use the ignore patterns: {err}");
+        GlobSet::empty()
+    })
+}
+
+/// The package candidates and folder listings found below a folder.
+#[derive(Default)]
+struct Walk {
+    candidates: Vec<SourcePackage>,
+    folders: Vec<(PathBuf, CachedFolder)>,
+}
+
+impl Walk {
+    fn append(mut self, mut other: Self) -> Self {
+        self.candidates.append(&mut other.candidates);
+        self.folders.append(&mut other.folders);
+        self
+    }
+}
+
+/// The folder currently being searched for packages.
+struct Folder<'a> {
+    path: PathBuf,
+    /// The path with all symlinks resolved, to notice symlink loops.
+    real_path: PathBuf,
+    depth: usize,
+    source_path: &'a Path,
+    patterns: &'a GlobSet,
+}
+
+impl Folder<'_> {
+    fn matches_ignore_pattern(&self) -> bool {
+        let relative_path = self
+            .path
+            .strip_prefix(self.source_path)
+            .unwrap_or(&self.path);
+        self.depth > 0 && self.patterns.is_match(relative_path)
+    }
+
+    /// The subfolders in `listing`, following symlinks.
+    fn children(&self, listing: &CachedFolder) -> Vec<Self> {
+        listing
+            .children
+            .iter()
+            .filter_map(|child| {
+                let path = self.path.join(&child.name);
+                let real_path = if child.symlink {
+                    let real_path = path.canonicalize().ok().filter(|path| path.is_dir())?;
+                    if self.real_path.starts_with(&real_path) {
+                        log::warn!("Skipping {} as it links to a parent", path.display());
+                        return None;
+                    }
+                    real_path
+                } else {
+                    self.real_path.join(&child.name)
+                };
+                Some(Folder {
+                    path,
+                    real_path,
+                    depth: self.depth + 1,
+                    source_path: self.source_path,
+                    patterns: self.patterns,
+                })
+            })
+            .collect()
+    }
+
+    /// The package candidates at or below this folder, searching sibling folders in parallel.
+    /// Packages do not nest, so the search stops at the first folder with a `package.xml`, and
+    /// never enters the huge vendored or generated trees packages may contain.
+    fn walk(&self, cache: &DiscoveryCache) -> Walk {
+        if self.matches_ignore_pattern() {
+            log::info!(
+                "Skipping {} as it matches an ignore pattern",
+                self.path.display()
+            );
+            return Walk::default();
+        }
 
-    fn get_all_package_paths(&self) -> HashMap<PackageName, Self::PackageType> {
+        let listing = match list_folder(&self.path, cache) {
+            Ok(listing) => listing,
+            Err(err) => {
+                log::warn!("Cannot read {}: {err}", self.path.display());
+                return Walk::default();
+            }
+        };
+        let mut walk = Walk {
+            candidates: vec![],
+            folders: vec![(self.path.clone(), listing.clone())],
+        };
+
+        if let Some(marker) = &listing.ignore_marker {
+            log::info!("Skipping {} as it contains {marker}", self.path.display());
+            return walk;
+        }
+        if listing.package_xml {
+            walk.candidates.push(SourcePackage::from(&self.path));
+            return walk;
+        }
+
+        let walks: Vec<_> = self
+            .children(&listing)
+            .par_iter()
+            .map(|child| child.walk(cache))
+            .collect();
+        walks.into_iter().fold(walk, Walk::append)
+    }
+}
+
+impl Source {
+    /// Like [`PackageContainer::get_all_package_paths`], but reusing what `cache` knows about
+    /// unchanged folders and packages. Afterwards, `cache` holds what this search found.
+    pub fn get_all_package_paths_cached(
+        &self,
+        config: &Config,
+        cache: &mut DiscoveryCache,
+    ) -> BTreeMap<PackageName, SourcePackage> {
         let Self(source_path) = self;
 
         log::debug!("Got source path: {}", source_path.display());
+        let patterns = ignore_patterns(&config.ignore);
+
+        let Ok(real_path) = source_path.canonicalize() else {
+            log::warn!("Cannot find source space {}", source_path.display());
+            return BTreeMap::new();
+        };
+        let root = Folder {
+            path: source_path.clone(),
+            real_path,
+            depth: 0,
+            source_path,
+            patterns: &patterns,
+        };
 
-        WalkDir::new(source_path)
-            .follow_links(true)
+        let walk = root.walk(cache);
+        let packages: Vec<_> = walk
+            .candidates
+            .into_par_iter()
+            .map(|package| {
+                let cached = cached_package(&package, cache);
+                (package, cached)
+            })
+            .collect();
+
+        // The walk lists packages in path order, so the first of several packages sharing a name
+        // is kept no matter how the search was scheduled.
+        let mut source_packages: BTreeMap<PackageName, SourcePackage> = BTreeMap::new();
+        for (package, cached) in &packages {
+            let Some(package_name) = cached.package_name.clone() else {
+                continue;
+            };
+            if let Some(kept) = source_packages.get(&package_name) {
+                log::warn!(
+                    "Ignoring {}, as {} is already called {package_name}; add an ignore marker or \
+                     pattern to choose another",
+                    package.path().display(),
+                    kept.path().display()
+                );
+                continue;
+            }
+            source_packages.insert(
+                package_name,
+                SourcePackage {
+                    path: package.path(),
+                    build_type: cached.build_type.clone(),
+                },
+            );
+        }
+
+        cache.folders = walk.folders.into_iter().collect();
+        cache.packages = packages
             .into_iter()
-            .filter_entry(|e| !is_hidden(e))
-            .filter_map(std::result::Result::ok)
-            .filter_map(|e| get_package_name_from_entry(&e))
+            .map(|(package, cached)| (package.path(), cached))
+            .collect();
+
+        source_packages
+    }
+}
+
+impl PackageContainer for Source {
+    type PackageType = SourcePackage;
+
+    fn get_all_package_paths(&self, config: &Config) -> BTreeMap<PackageName, Self::PackageType> {
+        let Self(source_path) = self;
+        self.get_all_package_paths_cached(config, &mut DiscoveryCache::empty(source_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeSet, path::Path, time::Duration};
+
+    use crate::{
+        cache::DiscoveryCache,
+        config::Config,
+        manifest::BuildType,
+        package_name::PackageName,
+        paths::{
+            package_container::PackageContainer, path::Path as _, source::ignore_patterns,
+            structs::Source,
+        },
+    };
+
+    /// Write a CMake package `name` into `path`.
+    fn write_package(path: &Path, name: &str) {
+        std::fs::create_dir_all(path).unwrap();
+        std::fs::write(
+            path.join("package.xml"),
+            format!("<package format=\"2\"><name>{name}</name><version>1.0.0</version></package>"),
+        )
+        .unwrap();
+        std::fs::write(path.join("CMakeLists.txt"), format!("project({name})\n")).unwrap();
+    }
+
+    /// Move the modification time of `path` forward, as changes within the timestamp granularity
+    /// of the filesystem would go unnoticed.
+    fn touch(path: &Path) {
+        let modified = std::fs::metadata(path).unwrap().modified().unwrap();
+        std::fs::File::open(path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+    }
+
+    fn package_names(source: &Source, cache: &mut DiscoveryCache) -> BTreeSet<String> {
+        source
+            .get_all_package_paths_cached(&Config::default(), cache)
+            .into_keys()
+            .map(|PackageName(name)| name)
             .collect()
     }
+
+    #[test]
+    fn test_cache_invalidation() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_path = dir.path().canonicalize().unwrap().join("src");
+        write_package(&source_path.join("foo"), "foo");
+        let source = Source::from(&source_path);
+        let mut cache = DiscoveryCache::empty(&source_path);
+        assert_eq!(
+            package_names(&source, &mut cache),
+            BTreeSet::from(["foo".into()])
+        );
+        assert_eq!(
+            package_names(&source, &mut cache),
+            BTreeSet::from(["foo".into()])
+        );
+
+        write_package(&source_path.join("foo"), "renamed");
+        assert_eq!(
+            package_names(&source, &mut cache),
+            BTreeSet::from(["renamed".into()])
+        );
+
+        std::fs::write(source_path.join("foo/CMakeLists.txt"), "project(other)\n").unwrap();
+        assert!(package_names(&source, &mut cache).is_empty());
+
+        write_package(&source_path.join("foo"), "foo");
+        write_package(&source_path.join("group/bar"), "bar");
+        touch(&source_path);
+        assert_eq!(
+            package_names(&source, &mut cache),
+            BTreeSet::from(["bar".into(), "foo".into()])
+        );
+
+        std::fs::write(source_path.join("group/CATKIN_IGNORE"), "").unwrap();
+        touch(&source_path.join("group"));
+        assert_eq!(
+            package_names(&source, &mut cache),
+            BTreeSet::from(["foo".into()])
+        );
+    }
+
+    #[test]
+    fn test_walk() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_path = dir.path().canonicalize().unwrap().join("src");
+        write_package(&source_path.join("a/foo"), "foo");
+        write_package(&source_path.join("b/foo"), "foo");
+        for (marker, name) in crate::config::IGNORE_MARKER_NAMES
+            .into_iter()
+            .zip(["x", "y", "z"])
+        {
+            write_package(&source_path.join(name).join("inner"), name);
+            std::fs::write(source_path.join(name).join(marker), "").unwrap();
+        }
+        write_package(&source_path.join("a/foo/nested"), "nested");
+        std::os::unix::fs::symlink(&source_path, source_path.join("a/loop")).unwrap();
+        let source = Source::from(&source_path);
+
+        for _ in 0..3 {
+            let packages = source.get_all_package_paths(&Config::default());
+            assert_eq!(
+                packages.keys().cloned().collect::<Vec<_>>(),
+                vec![PackageName("foo".to_string())]
+            );
+            assert_eq!(
+                packages[&PackageName("foo".to_string())].path(),
+                source_path.join("a/foo")
+            );
+        }
+    }
+
+    #[test]
+    fn test_cached_build_type() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_path = dir.path().canonicalize().unwrap().join("src");
+        write_package(&source_path.join("foo"), "foo");
+        write_package(&source_path.join("py"), "py");
+        std::fs::remove_file(source_path.join("py/CMakeLists.txt")).unwrap();
+        let source = Source::from(&source_path);
+        let mut cache = DiscoveryCache::empty(&source_path);
+
+        let packages = source.get_all_package_paths_cached(&Config::default(), &mut cache);
+        assert_eq!(
+            packages[&PackageName("foo".to_string())].build_type,
+            Some(BuildType::Catkin)
+        );
+        assert!(!packages.contains_key(&PackageName("py".to_string())));
+
+        std::fs::write(source_path.join("py/setup.py"), "").unwrap();
+        let packages = source.get_all_package_paths_cached(&Config::default(), &mut cache);
+        assert_eq!(
+            packages[&PackageName("py".to_string())].build_type,
+            Some(BuildType::Other("python".to_string()))
+        );
+        assert_eq!(
+            cache.packages[&source_path.join("py")].build_type,
+            Some(BuildType::Other("python".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ignore_patterns() {
+        let patterns = ignore_patterns(&[
+            "third_party".to_string(),
+            "**/test/fixtures".to_string(),
+            "[invalid".to_string(),
+        ]);
+
+        assert!(patterns.is_match(Path::new("third_party")));
+        assert!(patterns.is_match(Path::new("foo/test/fixtures")));
+        assert!(!patterns.is_match(Path::new("foo/third_party")));
+        assert!(!patterns.is_match(Path::new("foo/test")));
+    }
 }
@@ -1,14 +1,13 @@
-
-
-use crate::{paths::path::Path};
-
-
+use crate::{
+    config::Config, manifest::BuildType, paths::path::Path, profile::Profile,
+    workspace_kind::WorkspaceKind,
+};
 
 #[macro_export]
 macro_rules! generate_paths {
     ( $( $x:ident ), * ) => {
         $(
-            #[derive(Debug)]
+            #[derive(Clone, Debug)]
             pub struct $x(pub(crate) std::path::PathBuf);
 
             impl Path for $x {
@@ -35,47 +34,174 @@ macro_rules! generate_paths {
 }
 
 generate_paths![
-    Workspace,
     Build,
+    BuildCompileCommands,
     BuildPackage,
-    BuildPackageCompileCommands,
     Source,
-    SourcePackage,
-    SourcePackageCompileCommands,
     SourcePackageXML,
     SourcePackageCMakeLists
 ];
 
+/// A package in the source space.
+#[derive(Clone, Debug)]
+pub struct SourcePackage {
+    pub(crate) path: std::path::PathBuf,
+    /// The build type found when the package was discovered, so that its manifest need not be
+    /// parsed again.
+    pub(crate) build_type: Option<BuildType>,
+}
+
+impl Path for SourcePackage {
+    fn path(&self) -> std::path::PathBuf {
+        self.path.to_path_buf()
+    }
+}
+
+impl<P: AsRef<std::path::Path>> From<P> for SourcePackage {
+    fn from(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            build_type: None,
+        }
+    }
+}
+
+impl std::fmt::Display for SourcePackage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SourcePackage({:?})", self.path)
+    }
+}
+
 impl super::package::Package for SourcePackage {}
 
 impl super::package::Package for BuildPackage {}
 
-impl super::origin_file::OriginFile for BuildPackageCompileCommands {}
-
 impl super::origin_file::OriginFile for SourcePackageXML {}
 
 impl super::origin_file::OriginFile for SourcePackageCMakeLists {}
 
+#[derive(Debug)]
+pub struct Workspace {
+    pub(crate) path: std::path::PathBuf,
+    pub(crate) kind: WorkspaceKind,
+    pub(crate) profile: Option<Profile>,
+    pub(crate) build_space: Option<std::path::PathBuf>,
+    pub(crate) source_space: Option<std::path::PathBuf>,
+    /// The configuration in effect for this workspace.
+    pub(crate) config: Config,
+}
+
+impl Workspace {
+    pub fn kind(&self) -> WorkspaceKind {
+        self.kind
+    }
+
+    /// The catkin_tools profile in use, if this is a catkin_tools workspace.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+}
+
+impl Path for Workspace {
+    fn path(&self) -> std::path::PathBuf {
+        self.path.to_path_buf()
+    }
+}
+
+impl std::fmt::Display for Workspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} workspace at {}", self.kind, self.path.display())?;
+        if let Some(profile) = &self.profile {
+            write!(f, " (profile '{}')", profile.name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The root of the build space, which for `catkin_make` holds the workspace wide database.
+fn build_space(workspace: &Workspace) -> std::path::PathBuf {
+    let Workspace { path, kind, .. } = workspace;
+
+    if let Some(build_space) = &workspace.build_space {
+        return path.join(build_space);
+    }
+
+    match (kind, &workspace.profile) {
+        (WorkspaceKind::CatkinTools, Some(profile)) => path.join(&profile.build_space),
+        (WorkspaceKind::CatkinMakeIsolated, _) => path.join(crate::config::ISOLATED_BUILD_SPACE),
+        (
+            WorkspaceKind::CatkinTools
+            | WorkspaceKind::Colcon
+            | WorkspaceKind::CatkinMake
+            | WorkspaceKind::Custom,
+            _,
+        ) => path.join(crate::config::DEFAULT_BUILD_SPACE),
+    }
+}
+
 impl From<&Workspace> for Build {
-    fn from(Workspace(path): &Workspace) -> Self {
-        Self(path.join("build"))
+    fn from(workspace: &Workspace) -> Self {
+        match workspace.kind {
+            // The single database of catkin_make is split into one folder per package.
+            WorkspaceKind::CatkinMake => {
+                Self(build_space(workspace).join(crate::config::SPLIT_COMPILE_COMMANDS_DIR_NAME))
+            }
+            WorkspaceKind::CatkinTools
+            | WorkspaceKind::Colcon
+            | WorkspaceKind::CatkinMakeIsolated
+            | WorkspaceKind::Custom => Self(build_space(workspace)),
+        }
+    }
+}
+
+impl From<&Workspace> for BuildCompileCommands {
+    fn from(workspace: &Workspace) -> Self {
+        Self(build_space(workspace).join(&workspace.config.compile_commands_name))
     }
 }
 
 impl From<&Workspace> for Source {
-    fn from(Workspace(path): &Workspace) -> Self {
-        Self(path.join("src"))
+    fn from(workspace: &Workspace) -> Self {
+        let Workspace { path, kind, .. } = workspace;
+
+        if let Some(source_space) = &workspace.source_space {
+            return Self(path.join(source_space));
+        }
+
+        match (kind, &workspace.profile) {
+            (WorkspaceKind::CatkinTools, Some(profile)) => Self(path.join(&profile.source_space)),
+            // colcon accepts packages anywhere below the workspace root, but `src` is customary.
+            (WorkspaceKind::Colcon, _) => {
+                let source_path = path.join(crate::config::DEFAULT_SOURCE_SPACE);
+                if source_path.is_dir() {
+                    Self(source_path)
+                } else {
+                    Self(path.to_path_buf())
+                }
+            }
+            (
+                WorkspaceKind::CatkinTools
+                | WorkspaceKind::CatkinMakeIsolated
+                | WorkspaceKind::CatkinMake
+                | WorkspaceKind::Custom,
+                _,
+            ) => Self(path.join(crate::config::DEFAULT_SOURCE_SPACE)),
+        }
     }
 }
 
 impl From<&SourcePackage> for SourcePackageXML {
-    fn from(SourcePackage(path): &SourcePackage) -> Self {
+    fn from(SourcePackage { path, .. }: &SourcePackage) -> Self {
         Self(path.join("package.xml"))
     }
 }
 
 impl From<&SourcePackage> for SourcePackageCMakeLists {
-    fn from(SourcePackage(path): &SourcePackage) -> Self {
+    fn from(SourcePackage { path, .. }: &SourcePackage) -> Self {
         Self(path.join("CMakeLists.txt"))
     }
 }
@@ -0,0 +1,139 @@
+use core::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::Config,
+    parsers::profile::{profile_config_parser, profiles_parser},
+};
+
+#[derive(Clone, Debug)]
+pub struct UnknownProfileError(pub String);
+
+impl std::error::Error for UnknownProfileError {}
+
+impl Display for UnknownProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(name) = self;
+        write!(f, "Cannot find catkin profile '{name}'")
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    IO(std::io::Error),
+    Yaml(serde_yaml::Error),
+    UnknownProfile(UnknownProfileError),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Self::IO(value)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(value: serde_yaml::Error) -> Self {
+        Self::Yaml(value)
+    }
+}
+
+impl From<UnknownProfileError> for Error {
+    fn from(value: UnknownProfileError) -> Self {
+        Self::UnknownProfile(value)
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IO(err) => write!(f, "IO error: {err}"),
+            Self::Yaml(err) => write!(f, "Invalid catkin profile configuration: {err}"),
+            Self::UnknownProfile(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A `catkin_tools` profile, with its build and source spaces relative to the workspace root
+/// (or absolute, if configured that way).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub build_space: PathBuf,
+    pub source_space: PathBuf,
+    pub cmake_args: Vec<String>,
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    if path.is_file() {
+        log::debug!("Reading {}", path.display());
+        Ok(Some(std::fs::read_to_string(path)?))
+    } else {
+        log::debug!("No file at {}", path.display());
+        Ok(None)
+    }
+}
+
+fn active_profile_name(profiles_path: &Path) -> Result<String> {
+    let profiles = read_optional(&profiles_path.join(crate::config::PROFILES_FILE_NAME))?
+        .map(|doc| profiles_parser(&doc))
+        .transpose()?
+        .unwrap_or_default();
+
+    Ok(profiles
+        .active
+        .unwrap_or_else(|| crate::config::DEFAULT_PROFILE_NAME.to_string()))
+}
+
+impl Profile {
+    /// Load the profile called `name` from the workspace at `workspace_path`, or the active profile
+    /// if no name is given. Missing configuration files fall back to the catkin defaults.
+    pub fn load<P: AsRef<Path>>(
+        workspace_path: P,
+        name: Option<&str>,
+        config: &Config,
+    ) -> Result<Self> {
+        let profiles_path = workspace_path
+            .as_ref()
+            .join(&config.metadata_dir_name)
+            .join(crate::config::PROFILES_DIR_NAME);
+
+        let name = match name {
+            Some(name) => {
+                if !profiles_path.join(name).is_dir() {
+                    log::error!(
+                        "Cannot find profile '{name}' in {}",
+                        profiles_path.display()
+                    );
+                    return Err(Error::UnknownProfile(UnknownProfileError(name.to_string())));
+                }
+                name.to_string()
+            }
+            None => active_profile_name(&profiles_path)?,
+        };
+        log::info!("Using catkin profile: {name}");
+
+        let config = read_optional(
+            &profiles_path
+                .join(&name)
+                .join(crate::config::PROFILE_CONFIG_FILE_NAME),
+        )?
+        .map(|doc| profile_config_parser(&doc))
+        .transpose()?
+        .unwrap_or_default();
+
+        Ok(Self {
+            name,
+            build_space: config
+                .build_space
+                .unwrap_or_else(|| PathBuf::from(crate::config::DEFAULT_BUILD_SPACE)),
+            source_space: config
+                .source_space
+                .unwrap_or_else(|| PathBuf::from(crate::config::DEFAULT_SOURCE_SPACE)),
+            cmake_args: config.cmake_args.unwrap_or_default(),
+        })
+    }
+}
@@ -0,0 +1,171 @@
+use std::fmt::Display;
+
+use serde::{ser::SerializeStruct, Serialize};
+
+use crate::symlink::PlannedLink;
+
+/// The outcome of linking a single package, ordered from best to worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The compile commands were linked, or would be in a dry run.
+    Linked,
+    /// The source package already links to the compile commands of the built package.
+    Unchanged,
+    /// The built package is not among the source packages.
+    Skipped,
+    /// The package is not built with CMake, so it never has compile commands.
+    NotApplicable,
+    /// The built package has no compile commands database.
+    MissingCompileCommands,
+    /// The source package has a compile commands file symlinkccc refuses to replace.
+    Conflict,
+    /// Linking failed for another reason, e.g. an IO error.
+    Error,
+}
+
+impl Outcome {
+    const ALL: [Self; 7] = [
+        Self::Linked,
+        Self::Unchanged,
+        Self::Skipped,
+        Self::NotApplicable,
+        Self::MissingCompileCommands,
+        Self::Conflict,
+        Self::Error,
+    ];
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Linked => write!(f, "linked"),
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Skipped => write!(f, "skipped"),
+            Self::NotApplicable => write!(f, "not applicable"),
+            Self::MissingCompileCommands => write!(f, "missing database"),
+            Self::Conflict => write!(f, "conflict"),
+            Self::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Which outcomes make the whole run count as failed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ExitPolicy {
+    /// Never fail because of a single package.
+    Never,
+    /// Fail if a package could not be linked because of an error. This is the default, so that
+    /// packages built without compile commands do not fail e.g. `catkin build && symlinkccc`.
+    #[default]
+    Error,
+    /// Fail on errors, or if a package has a compile commands file which is not a symlink.
+    Conflict,
+    /// Fail on errors and conflicts, or if a built package has no compile commands database.
+    Missing,
+}
+
+impl ExitPolicy {
+    pub fn fails_on(self, outcome: Outcome) -> bool {
+        match self {
+            Self::Never => false,
+            Self::Error => outcome >= Outcome::Error,
+            Self::Conflict => outcome >= Outcome::Conflict,
+            Self::Missing => outcome >= Outcome::MissingCompileCommands,
+        }
+    }
+}
+
+/// The planned or performed link for every built package of a workspace.
+#[derive(Debug)]
+pub struct LinkReport(pub Vec<PlannedLink>);
+
+impl LinkReport {
+    pub fn count(&self, outcome: Outcome) -> usize {
+        let Self(planned_links) = self;
+        planned_links
+            .iter()
+            .filter(|planned_link| planned_link.action.outcome() == outcome)
+            .count()
+    }
+
+    pub fn fails(&self, policy: ExitPolicy) -> bool {
+        let Self(planned_links) = self;
+        planned_links
+            .iter()
+            .any(|planned_link| policy.fails_on(planned_link.action.outcome()))
+    }
+
+    pub fn summary(&self) -> Summary<'_> {
+        Summary(self)
+    }
+}
+
+impl Display for LinkReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(planned_links) = self;
+        for planned_link in planned_links {
+            writeln!(f, "{planned_link}")?;
+        }
+        write!(f, "\n{}", self.summary())
+    }
+}
+
+impl Serialize for LinkReport {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(planned_links) = self;
+        let mut report = serializer.serialize_struct("LinkReport", 2)?;
+        report.serialize_field("packages", planned_links)?;
+        report.serialize_field("summary", &self.summary())?;
+        report.end()
+    }
+}
+
+/// A table with the number of packages per outcome.
+pub struct Summary<'a>(&'a LinkReport);
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(report) = self;
+        writeln!(f, "{:<18} {:>8}", "outcome", "packages")?;
+        for outcome in Outcome::ALL {
+            writeln!(
+                f,
+                "{:<18} {:>8}",
+                outcome.to_string(),
+                report.count(outcome)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Summary<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(report) = self;
+        serializer.collect_map(
+            Outcome::ALL
+                .into_iter()
+                .map(|outcome| (outcome, report.count(outcome))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::report::{ExitPolicy, Outcome};
+
+    #[test]
+    fn test_exit_policy() {
+        assert!(!ExitPolicy::Never.fails_on(Outcome::Error));
+        assert!(ExitPolicy::Error.fails_on(Outcome::Error));
+        assert!(!ExitPolicy::Error.fails_on(Outcome::Conflict));
+        assert!(ExitPolicy::Conflict.fails_on(Outcome::Conflict));
+        assert!(!ExitPolicy::Conflict.fails_on(Outcome::MissingCompileCommands));
+        assert!(ExitPolicy::Missing.fails_on(Outcome::MissingCompileCommands));
+        assert!(!ExitPolicy::Missing.fails_on(Outcome::NotApplicable));
+        assert!(!ExitPolicy::Missing.fails_on(Outcome::Skipped));
+        assert!(!ExitPolicy::Missing.fails_on(Outcome::Unchanged));
+        assert!(!ExitPolicy::Missing.fails_on(Outcome::Linked));
+    }
+}
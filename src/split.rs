@@ -0,0 +1,243 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    compile_commands::{self, CompileCommand},
+    config::Config,
+    package_name::PackageName,
+    paths::{
+        modified,
+        package::Package,
+        path::Path,
+        structs::{Build, BuildPackage, SourcePackage},
+    },
+};
+
+/// Find the package whose source folder contains `file`, preferring the most deeply nested one.
+fn owning_package<'a>(
+    file: &std::path::Path,
+    packages: &'a [(PackageName, std::path::PathBuf)],
+) -> Option<&'a PackageName> {
+    packages
+        .iter()
+        .filter(|(_, package_path)| file.starts_with(package_path))
+        .max_by_key(|(_, package_path)| package_path.components().count())
+        .map(|(package_name, _)| package_name)
+}
+
+/// Group the entries of a workspace wide compile commands database by the source package
+/// containing the compiled file. Entries outside every package are dropped.
+pub fn assign_to_packages(
+    commands: Vec<CompileCommand>,
+    source_packages: &BTreeMap<PackageName, SourcePackage>,
+) -> BTreeMap<PackageName, Vec<CompileCommand>> {
+    let packages: Vec<_> = source_packages
+        .iter()
+        .map(|(package_name, source_package)| {
+            let path = source_package.path();
+            (package_name.clone(), path.canonicalize().unwrap_or(path))
+        })
+        .collect();
+
+    let mut assigned: BTreeMap<PackageName, Vec<CompileCommand>> = BTreeMap::new();
+    for command in commands {
+        let file = command.absolute_file();
+        if let Some(package_name) = owning_package(&file, &packages) {
+            assigned
+                .entry(package_name.clone())
+                .or_default()
+                .push(command);
+        } else {
+            log::debug!(
+                "Compiled file {} is not part of any source package",
+                file.display()
+            );
+        }
+    }
+    assigned
+}
+
+/// The packages which get their own database when splitting the database at `database_path`.
+pub fn split_package_names<P: AsRef<std::path::Path>>(
+    database_path: P,
+    source_packages: &BTreeMap<PackageName, SourcePackage>,
+) -> compile_commands::Result<BTreeSet<PackageName>> {
+    let commands = compile_commands::read(database_path)?;
+    Ok(assign_to_packages(commands, source_packages)
+        .into_keys()
+        .collect())
+}
+
+/// Split the single compile commands database at `database_path` into one database per package
+/// below `build`, so that they can be linked like the ones of any other workspace.
+/// Package databases which are newer than `database_path` are left as they are, and those of
+/// packages no longer in `database_path` are removed.
+pub fn split_compile_commands<P: AsRef<std::path::Path>>(
+    database_path: P,
+    source_packages: &BTreeMap<PackageName, SourcePackage>,
+    build: &Build,
+    config: &Config,
+) -> compile_commands::Result<()> {
+    let database_path = database_path.as_ref();
+    log::info!(
+        "Splitting compile commands database {}",
+        database_path.display()
+    );
+
+    let database_modified = modified(database_path);
+    let commands = compile_commands::read(database_path)?;
+    let assigned = assign_to_packages(commands, source_packages);
+    prune(build, &assigned, config)?;
+
+    for (package_name, commands) in assigned {
+        let build_package = BuildPackage(build.path().join(&package_name.0));
+        let package_compile_commands = build_package.compile_commands(config);
+
+        let package_modified = modified(&package_compile_commands);
+        if package_modified.is_some() && package_modified >= database_modified {
+            log::debug!("Compile commands for {package_name} are up to date");
+            continue;
+        }
+
+        log::debug!(
+            "Writing {} compile commands for {package_name}",
+            commands.len()
+        );
+        std::fs::create_dir_all(build_package.path())?;
+        compile_commands::write(package_compile_commands, &commands)?;
+    }
+
+    Ok(())
+}
+
+/// Remove the databases below `build` of the packages which are not in `assigned`.
+fn prune(
+    build: &Build,
+    assigned: &BTreeMap<PackageName, Vec<CompileCommand>>,
+    config: &Config,
+) -> std::io::Result<()> {
+    let Ok(entries) = std::fs::read_dir(build.path()) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(std::result::Result::ok) {
+        let Some(name) = entry.file_name().to_str().map(ToString::to_string) else {
+            continue;
+        };
+        if !entry.path().is_dir() || assigned.contains_key(&PackageName(name.clone())) {
+            continue;
+        }
+
+        let build_package = BuildPackage(entry.path());
+        let package_compile_commands = build_package.compile_commands(config);
+        if package_compile_commands.is_file() {
+            log::info!("Removing compile commands of {name}, which left the database");
+            std::fs::remove_file(package_compile_commands)?;
+        }
+        // Only succeeds if nothing else was put there.
+        let _ = std::fs::remove_dir(build_package.path());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{
+        compile_commands::{self, CompileCommand},
+        config::Config,
+        package_name::PackageName,
+        paths::structs::{Build, SourcePackage},
+        split::{assign_to_packages, split_compile_commands},
+    };
+
+    fn command(directory: &str, file: &str) -> CompileCommand {
+        CompileCommand {
+            directory: directory.into(),
+            file: file.into(),
+            command: Some(format!("c++ -c {file}")),
+            arguments: None,
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_assign_to_packages() {
+        let source_packages = BTreeMap::from([
+            (
+                PackageName("outer".to_string()),
+                SourcePackage::from("/ws/src/outer"),
+            ),
+            (
+                PackageName("inner".to_string()),
+                SourcePackage::from("/ws/src/outer/inner"),
+            ),
+            (
+                PackageName("other".to_string()),
+                SourcePackage::from("/ws/src/other"),
+            ),
+        ]);
+        let commands = vec![
+            command("/ws/build/outer", "/ws/src/outer/src/a.cpp"),
+            command("/ws/build/inner", "/ws/src/outer/inner/src/b.cpp"),
+            command("/ws/src/other", "src/c.cpp"),
+            command("/ws/build", "/ws/build/generated.cpp"),
+        ];
+
+        let assigned = assign_to_packages(commands, &source_packages);
+
+        assert_eq!(assigned.len(), 3);
+        assert_eq!(
+            assigned[&PackageName("outer".to_string())],
+            vec![command("/ws/build/outer", "/ws/src/outer/src/a.cpp")]
+        );
+        assert_eq!(
+            assigned[&PackageName("inner".to_string())],
+            vec![command("/ws/build/inner", "/ws/src/outer/inner/src/b.cpp")]
+        );
+        assert_eq!(
+            assigned[&PackageName("other".to_string())],
+            vec![command("/ws/src/other", "src/c.cpp")]
+        );
+    }
+
+    #[test]
+    fn test_prune_split_databases() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let build = Build::from(root.join("build/symlinkccc"));
+        let stale = root.join("build/symlinkccc/stale/compile_commands.json");
+        std::fs::create_dir_all(stale.parent().unwrap()).unwrap();
+        std::fs::write(&stale, "[]").unwrap();
+
+        let source = root.join("src/kept");
+        std::fs::create_dir_all(&source).unwrap();
+        let database = root.join("build/compile_commands.json");
+        let file = source.join("a.cpp");
+        compile_commands::write(
+            &database,
+            &[command(
+                root.join("build").to_str().unwrap(),
+                file.to_str().unwrap(),
+            )],
+        )
+        .unwrap();
+
+        let source_packages = BTreeMap::from([
+            (
+                PackageName("kept".to_string()),
+                SourcePackage::from(&source),
+            ),
+            (
+                PackageName("stale".to_string()),
+                SourcePackage::from(root.join("src/stale")),
+            ),
+        ]);
+        split_compile_commands(&database, &source_packages, &build, &Config::default()).unwrap();
+
+        assert!(root
+            .join("build/symlinkccc/kept/compile_commands.json")
+            .is_file());
+        assert!(!stale.exists());
+        assert!(!stale.parent().unwrap().exists());
+    }
+}
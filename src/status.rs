@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeSet,
+    fmt::Display,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::{
+    config::Config,
+    copy,
+    manifest::BuildType,
+    package_name::PackageName,
+    paths::{
+        modified,
+        package::Package,
+        package_container::PackageContainer,
+        path::Path,
+        structs::{Build, SourcePackage, Workspace},
+    },
+};
+
+/// The state of the compile commands of a single package.
+#[derive(Debug, Serialize)]
+pub struct PackageStatus {
+    #[serde(rename = "package")]
+    pub package_name: PackageName,
+    #[serde(rename = "source")]
+    pub source_path: Option<PathBuf>,
+    /// How the source package is built, where anything but CMake never has compile commands.
+    pub build_type: Option<BuildType>,
+    /// The build folder of the package exists.
+    pub built: bool,
+    /// The build folder contains a compile commands database.
+    pub database: bool,
+    /// The source package contains a compile commands link or copy made by symlinkccc.
+    pub link: bool,
+    /// The link or copy refers to the compile commands of the built package.
+    pub correct: bool,
+    /// The link points to a file which does not exist.
+    pub dangling: bool,
+    /// Seconds the database is newer than the newest file of the source package, negative if
+    /// the database is older.
+    pub database_age: Option<i64>,
+}
+
+fn seconds(time: SystemTime) -> Option<i64> {
+    let duration = time.duration_since(UNIX_EPOCH).ok()?;
+    i64::try_from(duration.as_secs()).ok()
+}
+
+/// The modification time of the newest file in `source_package`, ignoring hidden files and
+/// compile commands.
+fn newest_source_file(source_package: &SourcePackage, config: &Config) -> Option<SystemTime> {
+    WalkDir::new(source_package.path())
+        .into_iter()
+        .filter_entry(|e| {
+            e.depth() == 0
+                || !e
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|s| s.starts_with('.') || s == config.compile_commands_name)
+        })
+        .filter_map(std::result::Result::ok)
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| e.metadata().ok()?.modified().ok())
+        .max()
+}
+
+fn status(
+    package_name: PackageName,
+    source_package: Option<&SourcePackage>,
+    build: &Build,
+    config: &Config,
+) -> PackageStatus {
+    let build_path = build.path().join(&package_name.0);
+    let build_compile_commands = build_path.join(&config.compile_commands_name);
+    let source_compile_commands =
+        source_package.map(|source_package| source_package.compile_commands(config));
+
+    let is_symlink = source_compile_commands.as_ref().is_some_and(|path| {
+        std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink())
+    });
+    let is_copy = source_compile_commands
+        .as_ref()
+        .is_some_and(|path| !is_symlink && copy::is_copy(path));
+
+    // Follow the link rather than reading it, as the kernel resolves relative targets from the
+    // physical folder of the link.
+    let target = source_compile_commands.as_ref().and_then(|path| {
+        if is_symlink {
+            path.canonicalize().ok()
+        } else if is_copy {
+            copy::copied_from(path)?.canonicalize().ok()
+        } else {
+            None
+        }
+    });
+    let dangling = source_compile_commands
+        .as_ref()
+        .is_some_and(|path| is_symlink && std::fs::metadata(path).is_err());
+
+    let database_modified = modified(&build_compile_commands);
+    let database_age = database_modified
+        .zip(source_package.and_then(|source_package| newest_source_file(source_package, config)))
+        .and_then(|(database, source)| Some(seconds(database)? - seconds(source)?));
+
+    PackageStatus {
+        source_path: source_package.map(SourcePackage::path),
+        build_type: source_package.and_then(SourcePackage::build_type),
+        built: build_path.is_dir(),
+        database: database_modified.is_some(),
+        link: is_symlink || is_copy,
+        correct: target.is_some() && target == build_compile_commands.canonicalize().ok(),
+        dangling,
+        database_age,
+        package_name,
+    }
+}
+
+/// The status of every package found among either the source or the built packages, without
+/// writing the discovery cache in a `dry_run`.
+pub fn status_all_packages(workspace: &Workspace, dry_run: bool) -> Vec<PackageStatus> {
+    log::debug!("Current workspace: {workspace}");
+
+    let source_packages = crate::cache::source_packages(workspace, dry_run);
+    let build = Build::from(workspace);
+    let build_packages = build.get_all_package_paths(&workspace.config);
+
+    let package_names: BTreeSet<_> = source_packages
+        .keys()
+        .chain(build_packages.keys())
+        .cloned()
+        .collect();
+
+    package_names
+        .into_iter()
+        .map(|package_name| {
+            let source_package = source_packages.get(&package_name);
+            status(package_name, source_package, &build, &workspace.config)
+        })
+        .collect()
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// A short human readable form of a signed number of seconds, e.g. `+2h` or `-5m`.
+pub fn format_age(seconds: i64) -> String {
+    let sign = if seconds < 0 { '-' } else { '+' };
+    let seconds = seconds.unsigned_abs();
+    match seconds {
+        0..=59 => format!("{sign}{seconds}s"),
+        60..=3599 => format!("{sign}{}m", seconds / 60),
+        3600..=86399 => format!("{sign}{}h", seconds / 3600),
+        _ => format!("{sign}{}d", seconds / 86400),
+    }
+}
+
+/// A table with one row per package.
+pub struct StatusTable<'a>(pub &'a [PackageStatus]);
+
+impl Serialize for StatusTable<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Self(statuses) = self;
+        statuses.serialize(serializer)
+    }
+}
+
+impl Display for StatusTable<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(statuses) = self;
+        let width = statuses
+            .iter()
+            .map(|status| status.package_name.to_string().len())
+            .max()
+            .unwrap_or_default()
+            .max("package".len());
+
+        writeln!(
+            f,
+            "{:<width$}  {:<12}  {:<5}  {:<8}  {:<4}  {:<7}  {:<8}  {:<6}  source",
+            "package", "type", "build", "database", "link", "correct", "dangling", "age"
+        )?;
+        for status in *statuses {
+            writeln!(
+                f,
+                "{:<width$}  {:<12}  {:<5}  {:<8}  {:<4}  {:<7}  {:<8}  {:<6}  {}",
+                status.package_name.to_string(),
+                status
+                    .build_type
+                    .as_ref()
+                    .map_or_else(|| "-".to_string(), ToString::to_string),
+                yes_no(status.built),
+                yes_no(status.database),
+                yes_no(status.link),
+                yes_no(status.correct),
+                yes_no(status.dangling),
+                status
+                    .database_age
+                    .map_or_else(|| "-".to_string(), format_age),
+                status
+                    .source_path
+                    .as_ref()
+                    .map_or_else(|| "-".to_string(), |path| path.display().to_string()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::status::format_age;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(0), "+0s");
+        assert_eq!(format_age(-59), "-59s");
+        assert_eq!(format_age(120), "+2m");
+        assert_eq!(format_age(-7200), "-2h");
+        assert_eq!(format_age(3 * 86400 + 5), "+3d");
+    }
+}
@@ -1,9 +1,23 @@
+use std::{
+    collections::BTreeSet,
+    path::{Component, PathBuf},
+};
+
 use crate::{
+    compile_commands,
+    config::Config,
+    copy::{self, Rewrite},
+    manifest::BuildType,
+    package_name::PackageName,
     paths::{
         package::Package,
-        structs::{Build, BuildPackage, Source, SourcePackage},
+        package_container::PackageContainer,
+        path::Path,
+        structs::{Build, BuildCompileCommands, BuildPackage, SourcePackage, Workspace},
     },
-    workspace::find_enclosing,
+    report::{LinkReport, Outcome},
+    split::{split_compile_commands, split_package_names},
+    workspace_kind::WorkspaceKind,
 };
 
 #[derive(Debug, Clone)]
@@ -17,11 +31,28 @@ impl std::fmt::Display for NoCompileCommandsError {
 
 impl std::error::Error for NoCompileCommandsError {}
 
+#[derive(Debug, Clone)]
+pub struct ConflictError(pub PathBuf);
+
+impl std::fmt::Display for ConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self(path) = self;
+        write!(
+            f,
+            "Refusing to replace {}, which was not created by symlinkccc",
+            path.display()
+        )
+    }
+}
+
+impl std::error::Error for ConflictError {}
+
 #[derive(Debug)]
 pub enum Error {
     IO(std::io::Error),
-    Workspace(crate::workspace::Error),
     NoCompileCommands(NoCompileCommandsError),
+    CompileCommands(compile_commands::Error),
+    Conflict(ConflictError),
 }
 
 impl From<std::io::Error> for Error {
@@ -30,99 +61,757 @@ impl From<std::io::Error> for Error {
     }
 }
 
-impl From<crate::workspace::Error> for Error {
-    fn from(value: crate::workspace::Error) -> Self {
-        Self::Workspace(value)
-    }
-}
-
 impl From<NoCompileCommandsError> for Error {
     fn from(value: NoCompileCommandsError) -> Self {
         Self::NoCompileCommands(value)
     }
 }
 
+impl From<compile_commands::Error> for Error {
+    fn from(value: compile_commands::Error) -> Self {
+        Self::CompileCommands(value)
+    }
+}
+
+impl From<ConflictError> for Error {
+    fn from(value: ConflictError) -> Self {
+        Self::Conflict(value)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::IO(err) => write!(f, "IO error: {err}"),
+            Self::NoCompileCommands(err) => write!(f, "{err}"),
+            Self::CompileCommands(err) => write!(f, "{err}"),
+            Self::Conflict(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-fn link_compile_commands(
-    build_package_path: &BuildPackage,
-    source_package_path: &SourcePackage,
-) -> Result<()> {
-    log::info!("Linking compile commands");
+/// How the compile commands of a source package refer to the built compile commands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkMode {
+    /// A symlink to the absolute path of the built compile commands.
+    #[default]
+    Absolute,
+    /// A symlink relative to the source package, which survives moving the whole workspace.
+    Relative,
+    /// A copy of the built compile commands, with its paths rewritten.
+    Copy,
+}
+
+/// How to link compile commands.
+#[derive(Clone, Debug, Default)]
+pub struct LinkOptions {
+    /// Only plan the links, without touching the filesystem.
+    pub dry_run: bool,
+    /// Back up and replace compile commands files which symlinkccc did not create.
+    pub force: bool,
+    pub mode: LinkMode,
+    /// Path prefixes to rewrite when copying compile commands.
+    pub rewrites: Vec<Rewrite>,
+}
+
+/// What linking the compile commands of a single package does.
+#[derive(Debug)]
+pub enum Action {
+    /// Create a new link, as the source package has no compile commands file.
+    Create,
+    /// Nothing to do, as the source package already links to the right compile commands.
+    Unchanged,
+    /// Replace the existing compile commands link or copy of the source package.
+    Replace,
+    /// Move the existing compile commands file of the source package aside, and link in its place.
+    BackUp,
+    /// Nothing to do, as the built package is not among the source packages.
+    Skip,
+    /// Nothing to do, as the package is not built with CMake.
+    NotApplicable(BuildType),
+    /// The package cannot be linked.
+    Fail(Error),
+}
+
+impl Action {
+    pub fn outcome(&self) -> Outcome {
+        match self {
+            Self::Create | Self::Replace | Self::BackUp => Outcome::Linked,
+            Self::Unchanged => Outcome::Unchanged,
+            Self::Skip => Outcome::Skipped,
+            Self::NotApplicable(_) => Outcome::NotApplicable,
+            Self::Fail(Error::NoCompileCommands(_)) => Outcome::MissingCompileCommands,
+            Self::Fail(Error::Conflict(_)) => Outcome::Conflict,
+            Self::Fail(_) => Outcome::Error,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Create => write!(f, "create"),
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Replace => write!(f, "replace"),
+            Self::BackUp => write!(f, "backup"),
+            Self::Skip => write!(f, "skip"),
+            Self::NotApplicable(_) => write!(f, "n/a"),
+            Self::Fail(_) => write!(f, "fail"),
+        }
+    }
+}
+
+/// A planned link from the compile commands of a built package into its source package.
+#[derive(Debug)]
+pub struct PlannedLink {
+    pub package_name: PackageName,
+    pub build_compile_commands: PathBuf,
+    pub source_compile_commands: Option<PathBuf>,
+    /// What the link will contain, e.g. a path relative to the source package, or the compile
+    /// commands copied from.
+    pub link_contents: PathBuf,
+    pub action: Action,
+}
+
+impl std::fmt::Display for PlannedLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:<9} {}: ", self.action.to_string(), self.package_name)?;
+        match &self.source_compile_commands {
+            Some(source) => write!(
+                f,
+                "{} -> {}",
+                source.display(),
+                self.link_contents.display()
+            )?,
+            None => write!(f, "not found among the source packages")?,
+        }
+        match &self.action {
+            Action::Fail(err) => write!(f, " ({err})")?,
+            Action::NotApplicable(build_type) => write!(f, " ({build_type} package)")?,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl serde::Serialize for PlannedLink {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Record<'a> {
+            package: &'a PackageName,
+            source: Option<&'a PathBuf>,
+            build: &'a PathBuf,
+            link: &'a PathBuf,
+            action: String,
+            outcome: Outcome,
+            error: Option<String>,
+        }
+
+        Record {
+            package: &self.package_name,
+            source: self.source_compile_commands.as_ref(),
+            build: &self.build_compile_commands,
+            link: &self.link_contents,
+            action: self.action.to_string(),
+            outcome: self.action.outcome(),
+            error: match &self.action {
+                Action::Fail(err) => Some(err.to_string()),
+                _ => None,
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Resolve `..` and `.` components without touching the filesystem, as the target of a
+/// dangling link cannot be canonicalized.
+pub fn normalize(path: &std::path::Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The absolute target of the link at `path`, resolving a relative target from the physical
+/// folder of the link.
+pub fn link_target(path: &std::path::Path) -> std::io::Result<PathBuf> {
+    let target = std::fs::read_link(path)?;
+    let parent = path.parent().unwrap_or_else(|| std::path::Path::new("/"));
+    Ok(normalize(&canonicalize_existing(parent).join(target)))
+}
+
+/// The path leading from the folder `from` to `to`, where both are absolute and normalized.
+pub fn relative_path(from: &std::path::Path, to: &std::path::Path) -> PathBuf {
+    let from_components: Vec<_> = from.components().collect();
+    let to_components: Vec<_> = to.components().collect();
+
+    let common = from_components
+        .iter()
+        .zip(&to_components)
+        .take_while(|(from_component, to_component)| from_component == to_component)
+        .count();
+
+    std::iter::repeat_n(Component::ParentDir, from_components.len() - common)
+        .chain(to_components[common..].iter().copied())
+        .collect()
+}
+
+/// `path` with every symlink resolved, as far as it exists, as the kernel resolves `..` in link
+/// targets from the physical folder.
+pub(crate) fn canonicalize_existing(path: &std::path::Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => canonicalize_existing(parent).join(file_name),
+        _ => normalize(path),
+    }
+}
 
-    let build_package_compile_commands_path = build_package_path.compile_commands();
+/// What the link at `source_package_compile_commands_path` should contain.
+fn link_contents(
+    build_package_compile_commands_path: &std::path::Path,
+    source_package_compile_commands_path: &std::path::Path,
+    mode: LinkMode,
+) -> PathBuf {
+    match (mode, source_package_compile_commands_path.parent()) {
+        (LinkMode::Relative, Some(source_package_path)) => relative_path(
+            &canonicalize_existing(source_package_path),
+            &canonicalize_existing(build_package_compile_commands_path),
+        ),
+        (LinkMode::Absolute | LinkMode::Relative | LinkMode::Copy, _) => {
+            build_package_compile_commands_path.to_path_buf()
+        }
+    }
+}
+
+fn plan_link(
+    build_package_compile_commands_path: &std::path::Path,
+    source_package_compile_commands_path: &std::path::Path,
+    will_be_built: bool,
+    options: &LinkOptions,
+) -> Action {
     log::debug!(
         "Built compile commands file at: {}",
         build_package_compile_commands_path.display()
     );
-
-    let source_package_compile_commands_path = source_package_path.compile_commands();
     log::debug!(
         "Source path for linking compile commands at: {}",
         source_package_compile_commands_path.display()
     );
 
-    if source_package_compile_commands_path.exists()
-        && source_package_compile_commands_path.is_file()
-    {
-        // TODO: Find some better way of handling especially this.
-        // Potentially look into the catkin profile.
-        // Or remove only if build folder is removed.
-        // Or look into cargo watch
-        log::debug!(
-            "Removing existing symlink to: {}",
-            source_package_compile_commands_path.display()
-        );
-        std::fs::remove_file(&source_package_compile_commands_path)?;
-    }
-
-    if !build_package_compile_commands_path.exists() {
-        log::error!(
+    if !will_be_built && !build_package_compile_commands_path.exists() {
+        log::warn!(
             "Cannot find built compile commands file: {}",
             build_package_compile_commands_path.display()
         );
-        return Err(Error::NoCompileCommands(NoCompileCommandsError {}));
+        return Action::Fail(Error::NoCompileCommands(NoCompileCommandsError {}));
+    }
+
+    // Look at the link itself rather than its target, so that dangling links are replaced too.
+    match std::fs::symlink_metadata(source_package_compile_commands_path) {
+        Err(_) => Action::Create,
+        Ok(metadata) if metadata.is_symlink() && options.mode == LinkMode::Copy => Action::Replace,
+        Ok(metadata) if metadata.is_symlink() => {
+            let contents = link_contents(
+                build_package_compile_commands_path,
+                source_package_compile_commands_path,
+                options.mode,
+            );
+            match std::fs::read_link(source_package_compile_commands_path) {
+                Ok(target) if target == contents => {
+                    log::debug!("Existing symlink already points to the built compile commands");
+                    Action::Unchanged
+                }
+                _ => Action::Replace,
+            }
+        }
+        Ok(_) if copy::is_copy(source_package_compile_commands_path) => {
+            if options.mode == LinkMode::Copy
+                && copy::is_up_to_date(
+                    build_package_compile_commands_path,
+                    source_package_compile_commands_path,
+                    &options.rewrites,
+                )
+            {
+                log::debug!("Existing copy is newer than the built compile commands");
+                Action::Unchanged
+            } else {
+                Action::Replace
+            }
+        }
+        Ok(_) if options.force => Action::BackUp,
+        Ok(_) => {
+            log::warn!(
+                "Existing compile commands file is not a symlink: {}",
+                source_package_compile_commands_path.display()
+            );
+            Action::Fail(Error::Conflict(ConflictError(
+                source_package_compile_commands_path.to_path_buf(),
+            )))
+        }
+    }
+}
+
+/// Where to move the file at `path` aside to, without overwriting earlier backups: `path.orig`,
+/// or `path.orig.1`, `path.orig.2` and so on if that is taken.
+fn backup_path(path: &std::path::Path) -> PathBuf {
+    let mut backup_name = path.as_os_str().to_os_string();
+    backup_name.push(crate::config::BACKUP_SUFFIX);
+
+    let mut backup_path = PathBuf::from(&backup_name);
+    let mut number = 0;
+    while std::fs::symlink_metadata(&backup_path).is_ok() {
+        number += 1;
+        let mut numbered_name = backup_name.clone();
+        numbered_name.push(format!(".{number}"));
+        backup_path = PathBuf::from(numbered_name);
     }
+    backup_path
+}
+
+fn link_compile_commands(planned_link: &PlannedLink, options: &LinkOptions) -> Result<()> {
+    let PlannedLink {
+        package_name,
+        build_compile_commands,
+        source_compile_commands,
+        link_contents,
+        action,
+    } = planned_link;
+
+    let Some(source_compile_commands) = source_compile_commands else {
+        return Ok(());
+    };
 
-    std::os::unix::fs::symlink(
-        build_package_compile_commands_path,
-        source_package_compile_commands_path,
-    )?;
+    match action {
+        Action::Create => {}
+        Action::Replace if copy::is_copy(source_compile_commands) => {
+            log::debug!(
+                "Removing existing copy: {}",
+                source_compile_commands.display()
+            );
+            copy::remove_copy(source_compile_commands)?;
+        }
+        Action::Replace => {
+            log::debug!(
+                "Removing existing symlink: {}",
+                source_compile_commands.display()
+            );
+            std::fs::remove_file(source_compile_commands)?;
+        }
+        Action::BackUp => {
+            let backup_path = backup_path(source_compile_commands);
+            log::warn!(
+                "Moving existing compile commands to: {}",
+                backup_path.display()
+            );
+            std::fs::rename(source_compile_commands, backup_path)?;
+        }
+        Action::Unchanged | Action::Skip | Action::NotApplicable(_) | Action::Fail(_) => {
+            return Ok(())
+        }
+    }
+
+    if options.mode == LinkMode::Copy {
+        log::info!("Copying compile commands for {package_name}");
+        copy::copy_compile_commands(
+            build_compile_commands,
+            source_compile_commands,
+            &options.rewrites,
+        )?;
+        log::info!("Copied compile commands");
+        return Ok(());
+    }
+
+    log::info!("Linking compile commands for {package_name}");
+    std::os::unix::fs::symlink(link_contents, source_compile_commands)?;
     log::info!("Linked compile commands");
 
     Ok(())
 }
-use crate::paths::package_container::PackageContainer;
 
-pub fn link_all_compile_commands() -> Result<()> {
-    let current_working_directory = std::env::current_dir()?;
-    log::debug!(
-        "Current working directory: {}",
-        current_working_directory.display()
+/// Work out what linking a single built package into `source_package` would do.
+/// `will_be_built` marks compile commands which do not exist yet, but will before linking.
+pub fn plan_package(
+    package_name: PackageName,
+    build_package: &BuildPackage,
+    source_package: Option<&SourcePackage>,
+    will_be_built: bool,
+    options: &LinkOptions,
+    config: &Config,
+) -> PlannedLink {
+    let build_compile_commands = build_package.compile_commands(config);
+    let source_compile_commands =
+        source_package.map(|source_package| source_package.compile_commands(config));
+
+    let build_type = source_package.and_then(SourcePackage::build_type);
+    let action = if let Some(build_type) = build_type.filter(|build_type| !build_type.uses_cmake())
+    {
+        log::info!("Not linking {package_name}, as it is a {build_type} package");
+        Action::NotApplicable(build_type)
+    } else if let Some(source) = &source_compile_commands {
+        log::debug!("Planning link for {package_name}");
+        plan_link(&build_compile_commands, source, will_be_built, options)
+    } else {
+        log::warn!("Built package '{package_name}' cannot be found among the source packages.");
+        log::info!("This might be because this 'package' is only an umbrella for other packages, and therefore doens't show up in `rospack list`.");
+        Action::Skip
+    };
+
+    let link_contents = source_compile_commands.as_ref().map_or_else(
+        || build_compile_commands.clone(),
+        |source| link_contents(&build_compile_commands, source, options.mode),
     );
 
-    let workspace = find_enclosing(current_working_directory)?;
+    PlannedLink {
+        package_name,
+        build_compile_commands,
+        source_compile_commands,
+        link_contents,
+        action,
+    }
+}
+
+/// Perform `planned_link`, turning its action into a failure if linking goes wrong.
+pub fn apply(planned_link: &mut PlannedLink, options: &LinkOptions) {
+    if let Err(err) = link_compile_commands(planned_link, options) {
+        log::error!(
+            "Cannot link compile commands for {}: {err}",
+            planned_link.package_name
+        );
+        planned_link.action = Action::Fail(err);
+    }
+}
+
+/// Work out what linking every built package of `workspace` would do, without changing anything.
+/// The workspace wide database of a `catkin_make` workspace is only split outside of dry runs.
+pub fn plan_all_compile_commands(
+    workspace: &Workspace,
+    options: &LinkOptions,
+) -> Result<Vec<PlannedLink>> {
     log::debug!("Current workspace: {workspace}");
 
-    let source = Source::from(&workspace);
-    let source_packages = source.get_all_package_paths();
+    let source_packages = crate::cache::source_packages(workspace, options.dry_run);
     log::debug!("All package names to package sources\n: {source_packages:?}");
 
-    let build = Build::from(&workspace);
-    let build_packages = build.get_all_package_paths();
+    let build = Build::from(workspace);
+    let mut split_packages = BTreeSet::new();
+    if workspace.kind == WorkspaceKind::CatkinMake {
+        let BuildCompileCommands(database_path) = BuildCompileCommands::from(workspace);
+        if options.dry_run {
+            split_packages = split_package_names(database_path, &source_packages)?;
+        } else {
+            split_compile_commands(database_path, &source_packages, &build, &workspace.config)?;
+        }
+    }
+
+    let mut build_packages = build.get_all_package_paths(&workspace.config);
+    for package_name in &split_packages {
+        build_packages
+            .entry(package_name.clone())
+            .or_insert_with(|| BuildPackage(build.path().join(&package_name.0)));
+    }
     log::debug!("All package names to built packages\n: {build_packages:?}");
 
-    for (package_name, build_package) in build_packages {
-        if let Some(source_package) = source_packages.get(&package_name) {
-            log::debug!("Linking {package_name}");
-            log::debug!("From {build_package:?}");
-            log::debug!("To {source_package:?}");
-            link_compile_commands(&build_package, source_package)?;
-        } else {
-            log::warn!("Built package '{package_name}' cannot be found among the source packages.");
-            log::info!("This might be because this 'package' is only an umbrella for other packages, and therefore doens't show up in `rospack list`.");
+    Ok(build_packages
+        .into_iter()
+        .map(|(package_name, build_package)| {
+            let will_be_built = split_packages.contains(&package_name);
+            let source_package = source_packages.get(&package_name);
+            plan_package(
+                package_name,
+                &build_package,
+                source_package,
+                will_be_built,
+                options,
+                &workspace.config,
+            )
+        })
+        .collect())
+}
+
+/// Link every built package of `workspace`, carrying on past packages which cannot be linked.
+/// Only errors which affect the whole workspace are returned, the outcome for each package is
+/// collected in the report.
+pub fn link_all_compile_commands(
+    workspace: &Workspace,
+    options: &LinkOptions,
+) -> Result<LinkReport> {
+    let mut planned_links = plan_all_compile_commands(workspace, options)?;
+
+    if !options.dry_run {
+        for planned_link in &mut planned_links {
+            apply(planned_link, options);
         }
     }
 
-    Ok(())
+    Ok(LinkReport(planned_links))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use crate::{
+        config::Config,
+        package_name::PackageName,
+        paths::structs::{BuildPackage, SourcePackage},
+        report::{LinkReport, Outcome},
+        symlink::{
+            apply, backup_path, link_all_compile_commands, link_contents, normalize, plan_package,
+            relative_path, Action, LinkMode, LinkOptions, PlannedLink,
+        },
+        workspace::WorkspaceOptions,
+    };
+
+    /// A built package `foo` in `root/build`, and its source package in `root/src`.
+    fn package_fixture() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let build = root.join("build/foo/compile_commands.json");
+        std::fs::create_dir_all(build.parent().unwrap()).unwrap();
+        std::fs::write(&build, "[]").unwrap();
+        std::fs::create_dir_all(root.join("src/foo")).unwrap();
+        let source = root.join("src/foo/compile_commands.json");
+        (dir, build, source)
+    }
+
+    fn link(
+        build: &std::path::Path,
+        source: &std::path::Path,
+        options: &LinkOptions,
+    ) -> PlannedLink {
+        let mut planned_link = plan_package(
+            PackageName("foo".to_string()),
+            &BuildPackage::from(build.parent().unwrap()),
+            Some(&SourcePackage::from(source.parent().unwrap())),
+            false,
+            options,
+            &Config::default(),
+        );
+        apply(&mut planned_link, options);
+        planned_link
+    }
+
+    #[test]
+    fn test_link_keeps_real_file() {
+        let (_dir, build, source) = package_fixture();
+        std::fs::write(&source, "handwritten").unwrap();
+
+        let planned_link = link(&build, &source, &LinkOptions::default());
+        assert_eq!(planned_link.action.outcome(), Outcome::Conflict);
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "handwritten");
+        assert!(!source.with_extension("json.orig").exists());
+
+        let options = LinkOptions {
+            force: true,
+            ..LinkOptions::default()
+        };
+        let planned_link = link(&build, &source, &options);
+        assert_eq!(planned_link.action.outcome(), Outcome::Linked);
+        assert_eq!(std::fs::read_link(&source).unwrap(), build);
+        assert_eq!(
+            std::fs::read_to_string(source.with_extension("json.orig")).unwrap(),
+            "handwritten"
+        );
+    }
+
+    #[test]
+    fn test_link_replaces_symlinks() {
+        let (dir, build, source) = package_fixture();
+        let foreign = dir.path().join("foreign.json");
+        std::fs::write(&foreign, "[]").unwrap();
+
+        std::os::unix::fs::symlink(&foreign, &source).unwrap();
+        let planned_link = link(&build, &source, &LinkOptions::default());
+        assert!(matches!(planned_link.action, Action::Replace));
+        assert_eq!(std::fs::read_link(&source).unwrap(), build);
+        assert!(foreign.exists());
+
+        let planned_link = link(&build, &source, &LinkOptions::default());
+        assert!(matches!(planned_link.action, Action::Unchanged));
+
+        std::fs::remove_file(&source).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("gone.json"), &source).unwrap();
+        let planned_link = link(&build, &source, &LinkOptions::default());
+        assert!(matches!(planned_link.action, Action::Replace));
+        assert_eq!(std::fs::read_link(&source).unwrap(), build);
+    }
+
+    #[test]
+    fn test_link_replaces_copy() {
+        let (_dir, build, source) = package_fixture();
+        let copy = LinkOptions {
+            mode: LinkMode::Copy,
+            ..LinkOptions::default()
+        };
+
+        let planned_link = link(&build, &source, &copy);
+        assert!(matches!(planned_link.action, Action::Create));
+        assert!(crate::copy::is_copy(&source));
+
+        let planned_link = link(&build, &source, &LinkOptions::default());
+        assert!(matches!(planned_link.action, Action::Replace));
+        assert_eq!(std::fs::read_link(&source).unwrap(), build);
+        assert!(!crate::copy::is_copy(&source));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(&PathBuf::from(
+                "/ws/src/pkg/../../build/pkg/./compile_commands.json"
+            )),
+            PathBuf::from("/ws/build/pkg/compile_commands.json")
+        );
+        assert_eq!(normalize(&PathBuf::from("/..")), PathBuf::from("/"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(
+                &PathBuf::from("/ws/src/group/pkg"),
+                &PathBuf::from("/ws/build/pkg/compile_commands.json")
+            ),
+            PathBuf::from("../../../build/pkg/compile_commands.json")
+        );
+        assert_eq!(
+            relative_path(&PathBuf::from("/ws"), &PathBuf::from("/ws/build")),
+            PathBuf::from("build")
+        );
+    }
+
+    /// Every path below `root`, with the target of links and the contents of files.
+    fn snapshot(root: &std::path::Path) -> Vec<(PathBuf, String)> {
+        walkdir::WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .map(|entry| {
+                let entry = entry.unwrap();
+                let contents = if entry.path_is_symlink() {
+                    format!("-> {}", std::fs::read_link(entry.path()).unwrap().display())
+                } else if entry.file_type().is_file() {
+                    std::fs::read_to_string(entry.path()).unwrap()
+                } else {
+                    String::new()
+                };
+                (entry.into_path(), contents)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dry_run_leaves_catkin_make_workspace_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::write(root.join(".catkin_workspace"), "").unwrap();
+        let package = root.join("src/foo");
+        std::fs::create_dir_all(&package).unwrap();
+        std::fs::write(
+            package.join("package.xml"),
+            "<package format=\"2\"><name>foo</name><version>1.0.0</version></package>",
+        )
+        .unwrap();
+        std::fs::write(package.join("CMakeLists.txt"), "project(foo)\n").unwrap();
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        crate::compile_commands::write(
+            root.join("build/compile_commands.json"),
+            &[crate::compile_commands::CompileCommand {
+                directory: root.join("build/foo"),
+                file: package.join("a.cpp"),
+                command: Some("c++ -c a.cpp".to_string()),
+                arguments: None,
+                output: None,
+            }],
+        )
+        .unwrap();
+
+        let workspace = crate::workspace::open(&root, &WorkspaceOptions::default()).unwrap();
+        let before = snapshot(&root);
+        let options = LinkOptions {
+            dry_run: true,
+            mode: LinkMode::Relative,
+            ..LinkOptions::default()
+        };
+        let LinkReport(planned_links) = link_all_compile_commands(&workspace, &options).unwrap();
+        assert_eq!(snapshot(&root), before);
+
+        assert_eq!(planned_links.len(), 1);
+        assert!(matches!(planned_links[0].action, Action::Create));
+        assert_eq!(
+            planned_links[0].link_contents,
+            PathBuf::from("../../build/symlinkccc/foo/compile_commands.json")
+        );
+        assert!(planned_links[0]
+            .to_string()
+            .ends_with("-> ../../build/symlinkccc/foo/compile_commands.json"));
+    }
+
+    #[test]
+    fn test_relative_link_into_symlinked_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let build = root.join("ws/build/foo/compile_commands.json");
+        std::fs::create_dir_all(build.parent().unwrap()).unwrap();
+        std::fs::write(&build, "[]").unwrap();
+        std::fs::create_dir_all(root.join("ws/src")).unwrap();
+        std::fs::create_dir_all(root.join("elsewhere/foo")).unwrap();
+        std::os::unix::fs::symlink(root.join("elsewhere/foo"), root.join("ws/src/foo")).unwrap();
+
+        let source = root.join("ws/src/foo/compile_commands.json");
+        let contents = link_contents(&build, &source, LinkMode::Relative);
+        assert_eq!(
+            contents,
+            PathBuf::from("../../ws/build/foo/compile_commands.json")
+        );
+
+        std::os::unix::fs::symlink(&contents, &source).unwrap();
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_backup_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("compile_commands.json");
+        assert_eq!(
+            backup_path(&path),
+            dir.path().join("compile_commands.json.orig")
+        );
+
+        std::fs::write(
+            dir.path().join("compile_commands.json.orig"),
+            "handwritten1",
+        )
+        .unwrap();
+        assert_eq!(
+            backup_path(&path),
+            dir.path().join("compile_commands.json.orig.1")
+        );
+
+        std::fs::write(
+            dir.path().join("compile_commands.json.orig.1"),
+            "handwritten2",
+        )
+        .unwrap();
+        assert_eq!(
+            backup_path(&path),
+            dir.path().join("compile_commands.json.orig.2")
+        );
+    }
 }
@@ -0,0 +1,253 @@
+use std::path::PathBuf;
+
+use crate::{
+    config::Config,
+    copy,
+    package_name::PackageName,
+    paths::{
+        package::Package,
+        path::Path,
+        structs::{Build, SourcePackage, Workspace},
+    },
+    symlink::{canonicalize_existing, link_target},
+};
+
+/// Why an existing compile commands file was left in place.
+#[derive(Debug)]
+pub enum Refusal {
+    /// The file is a regular file, which symlinkccc did not copy there.
+    NotASymlink,
+    /// The file is a link, but not into the build space of the workspace.
+    ForeignTarget(PathBuf),
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotASymlink => write!(f, "not a symlink or a copy made by symlinkccc"),
+            Self::ForeignTarget(target) => {
+                write!(f, "links outside the build space to {}", target.display())
+            }
+        }
+    }
+}
+
+/// What unlinking the compile commands of a single source package does.
+#[derive(Debug)]
+pub enum Outcome {
+    Removed,
+    Refused(Refusal),
+    Failed(std::io::Error),
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Removed => write!(f, "removed"),
+            Self::Refused(_) => write!(f, "refused"),
+            Self::Failed(_) => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Unlinked {
+    pub package_name: PackageName,
+    pub source_compile_commands: PathBuf,
+    pub outcome: Outcome,
+}
+
+impl serde::Serialize for Unlinked {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Record<'a> {
+            package: &'a PackageName,
+            source: &'a PathBuf,
+            outcome: String,
+            reason: Option<String>,
+        }
+
+        Record {
+            package: &self.package_name,
+            source: &self.source_compile_commands,
+            outcome: self.outcome.to_string(),
+            reason: match &self.outcome {
+                Outcome::Removed => None,
+                Outcome::Refused(refusal) => Some(refusal.to_string()),
+                Outcome::Failed(err) => Some(err.to_string()),
+            },
+        }
+        .serialize(serializer)
+    }
+}
+
+impl std::fmt::Display for Unlinked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:<9} {}: {}",
+            self.outcome.to_string(),
+            self.package_name,
+            self.source_compile_commands.display()
+        )?;
+        match &self.outcome {
+            Outcome::Removed => Ok(()),
+            Outcome::Refused(refusal) => write!(f, " ({refusal})"),
+            Outcome::Failed(err) => write!(f, " ({err})"),
+        }
+    }
+}
+
+fn check(
+    source_compile_commands: &std::path::Path,
+    build_path: &std::path::Path,
+) -> std::io::Result<Option<Refusal>> {
+    if !std::fs::symlink_metadata(source_compile_commands)?.is_symlink() {
+        if copy::is_copy(source_compile_commands) {
+            return Ok(None);
+        }
+        return Ok(Some(Refusal::NotASymlink));
+    }
+
+    let target = link_target(source_compile_commands)?;
+    if target.starts_with(build_path) || target.starts_with(canonicalize_existing(build_path)) {
+        Ok(None)
+    } else {
+        Ok(Some(Refusal::ForeignTarget(target)))
+    }
+}
+
+/// Remove the compile commands symlinkccc created in `source_package`, unless `dry_run` is set.
+/// Returns nothing if the source package has no compile commands file.
+pub fn unlink_package(
+    package_name: PackageName,
+    source_package: &SourcePackage,
+    build_path: &std::path::Path,
+    config: &Config,
+    dry_run: bool,
+) -> Option<Unlinked> {
+    let source_compile_commands = source_package.compile_commands(config);
+    if std::fs::symlink_metadata(&source_compile_commands).is_err() {
+        log::debug!("No compile commands to remove for {package_name}");
+        return None;
+    }
+
+    let outcome = match check(&source_compile_commands, build_path) {
+        Ok(Some(refusal)) => {
+            log::warn!(
+                "Refusing to remove {}: {refusal}",
+                source_compile_commands.display()
+            );
+            Outcome::Refused(refusal)
+        }
+        Ok(None) if dry_run => Outcome::Removed,
+        Ok(None) => {
+            log::info!("Removing {}", source_compile_commands.display());
+            if copy::is_copy(&source_compile_commands) {
+                copy::remove_copy(&source_compile_commands)
+            } else {
+                std::fs::remove_file(&source_compile_commands)
+            }
+            .map_or_else(Outcome::Failed, |()| Outcome::Removed)
+        }
+        Err(err) => Outcome::Failed(err),
+    };
+
+    Some(Unlinked {
+        package_name,
+        source_compile_commands,
+        outcome,
+    })
+}
+
+/// Find the compile commands links and copies symlinkccc created in the source packages of
+/// `workspace`, and remove them unless `dry_run` is set. Source packages without a compile
+/// commands file are left out.
+pub fn unlink_all_compile_commands(workspace: &Workspace, dry_run: bool) -> Vec<Unlinked> {
+    log::debug!("Current workspace: {workspace}");
+
+    let build_path = Build::from(workspace).path();
+    let source_packages = crate::cache::source_packages(workspace, dry_run);
+
+    source_packages
+        .into_iter()
+        .filter_map(|(package_name, source_package)| {
+            unlink_package(
+                package_name,
+                &source_package,
+                &build_path,
+                &workspace.config,
+                dry_run,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::Config,
+        package_name::PackageName,
+        paths::structs::SourcePackage,
+        unlink::{unlink_package, Outcome, Refusal},
+    };
+
+    fn unlink(source: &std::path::Path, build: &std::path::Path, dry_run: bool) -> Outcome {
+        unlink_package(
+            PackageName("foo".to_string()),
+            &SourcePackage::from(source.parent().unwrap()),
+            build,
+            &Config::default(),
+            dry_run,
+        )
+        .unwrap()
+        .outcome
+    }
+
+    #[test]
+    fn test_unlink_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let build = root.join("build");
+        let built = build.join("foo/compile_commands.json");
+        std::fs::create_dir_all(built.parent().unwrap()).unwrap();
+        std::fs::write(&built, "[]").unwrap();
+        std::fs::create_dir_all(root.join("src/foo")).unwrap();
+        let source = root.join("src/foo/compile_commands.json");
+
+        assert!(unlink_package(
+            PackageName("foo".to_string()),
+            &SourcePackage::from(root.join("src/foo")),
+            &build,
+            &Config::default(),
+            false
+        )
+        .is_none());
+
+        std::os::unix::fs::symlink(&built, &source).unwrap();
+        assert!(matches!(unlink(&source, &build, true), Outcome::Removed));
+        assert!(std::fs::symlink_metadata(&source).is_ok());
+        assert!(matches!(unlink(&source, &build, false), Outcome::Removed));
+        assert!(std::fs::symlink_metadata(&source).is_err());
+
+        std::os::unix::fs::symlink("../../build/foo/compile_commands.json", &source).unwrap();
+        assert!(matches!(unlink(&source, &build, false), Outcome::Removed));
+        assert!(std::fs::symlink_metadata(&source).is_err());
+
+        let foreign = root.join("foreign.json");
+        std::os::unix::fs::symlink(&foreign, &source).unwrap();
+        assert!(matches!(
+            unlink(&source, &build, false),
+            Outcome::Refused(Refusal::ForeignTarget(target)) if target == foreign
+        ));
+        assert!(std::fs::symlink_metadata(&source).is_ok());
+        std::fs::remove_file(&source).unwrap();
+
+        std::fs::write(&source, "handwritten").unwrap();
+        assert!(matches!(
+            unlink(&source, &build, false),
+            Outcome::Refused(Refusal::NotASymlink)
+        ));
+        assert_eq!(std::fs::read_to_string(&source).unwrap(), "handwritten");
+    }
+}
@@ -0,0 +1,350 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{
+    config::Config,
+    output::{self, Format},
+    package_name::PackageName,
+    paths::{
+        package::Package,
+        package_container::PackageContainer,
+        path::Path,
+        structs::{Build, BuildCompileCommands, BuildPackage, SourcePackage, Workspace},
+    },
+    report::LinkReport,
+    symlink::{self, apply, link_all_compile_commands, plan_package, Action, LinkOptions},
+    unlink::unlink_package,
+    workspace_kind::WorkspaceKind,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    Notify(notify::Error),
+    Link(symlink::Error),
+}
+
+impl From<notify::Error> for Error {
+    fn from(value: notify::Error) -> Self {
+        Self::Notify(value)
+    }
+}
+
+impl From<symlink::Error> for Error {
+    fn from(value: symlink::Error) -> Self {
+        Self::Link(value)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Notify(err) => write!(f, "Cannot watch the build space: {err}"),
+            Self::Link(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What has to be relinked after a batch of filesystem events.
+#[derive(Debug, Default)]
+struct Pending {
+    packages: BTreeSet<PackageName>,
+    workspace: bool,
+}
+
+impl Pending {
+    fn is_empty(&self) -> bool {
+        self.packages.is_empty() && !self.workspace
+    }
+}
+
+struct BuildWatcher<'a> {
+    workspace: &'a Workspace,
+    options: &'a LinkOptions,
+    format: Format,
+    build_path: PathBuf,
+    workspace_compile_commands: Option<PathBuf>,
+    source_packages: BTreeMap<PackageName, SourcePackage>,
+    watcher: RecommendedWatcher,
+}
+
+fn print_relevant(format: Format, report: &LinkReport) {
+    let LinkReport(planned_links) = report;
+    for planned_link in planned_links {
+        if !matches!(
+            planned_link.action,
+            Action::Unchanged | Action::NotApplicable(_)
+        ) {
+            output::print(format, planned_link);
+        }
+    }
+}
+
+/// The built package an event at `path` concerns: either a package folder directly in
+/// `build_path`, or the compile commands database within one. Other files are ignored, as a
+/// running build touches thousands of them.
+fn package_name(
+    path: &std::path::Path,
+    build_path: &std::path::Path,
+    config: &Config,
+) -> Option<PackageName> {
+    let package_path = if path.parent() == Some(build_path) {
+        path
+    } else if path.file_name()? == config.compile_commands_name.as_str() {
+        path.parent()
+            .filter(|parent| parent.parent() == Some(build_path))?
+    } else {
+        return None;
+    };
+
+    let name = package_path.file_name()?.to_str()?;
+    if config
+        .build_ignore_dirs
+        .iter()
+        .any(|ignored| ignored == name)
+    {
+        return None;
+    }
+    Some(PackageName(name.to_string()))
+}
+
+impl BuildWatcher<'_> {
+    fn watch(&mut self, path: &std::path::Path) {
+        log::debug!("Watching {}", path.display());
+        if let Err(err) = self.watcher.watch(path, RecursiveMode::NonRecursive) {
+            log::warn!("Cannot watch {}: {err}", path.display());
+        }
+    }
+
+    /// Watch the build space and each package folder in it. Package folders are watched
+    /// one by one rather than recursively, as the build trees below them are huge and busy.
+    fn watch_build_space(&mut self) {
+        if let Some(parent) = self.build_path.parent().map(std::path::Path::to_path_buf) {
+            self.watch(&parent);
+        }
+        if !self.build_path.is_dir() {
+            log::info!(
+                "Build space {} does not exist yet",
+                self.build_path.display()
+            );
+            return;
+        }
+
+        self.watch(&self.build_path.clone());
+        let build_packages =
+            Build(self.build_path.clone()).get_all_package_paths(&self.workspace.config);
+        for build_package in build_packages.values() {
+            self.watch(&build_package.path());
+        }
+    }
+
+    fn handle(&mut self, event: &Event, pending: &mut Pending) {
+        for path in &event.paths {
+            log::trace!("{:?} {}", event.kind, path.display());
+
+            if Some(path) == self.workspace_compile_commands.as_ref() {
+                pending.workspace = true;
+            } else if path == &self.build_path {
+                if event.kind.is_create() {
+                    self.watch_build_space();
+                }
+                pending.packages.extend(
+                    Build(self.build_path.clone())
+                        .get_all_package_paths(&self.workspace.config)
+                        .into_keys(),
+                );
+            } else if let Some(package_name) =
+                package_name(path, &self.build_path, &self.workspace.config)
+            {
+                if event.kind.is_create() && path.is_dir() {
+                    self.watch(path);
+                }
+                pending.packages.insert(package_name);
+            }
+        }
+    }
+
+    fn source_package(&mut self, package_name: &PackageName) -> Option<SourcePackage> {
+        if !self.source_packages.contains_key(package_name) {
+            log::debug!("{package_name} is not a known source package, looking for new packages");
+            self.source_packages =
+                crate::cache::source_packages(self.workspace, self.options.dry_run);
+        }
+        self.source_packages.get(package_name).cloned()
+    }
+
+    fn relink(&mut self, pending: Pending) -> Result<()> {
+        if pending.workspace {
+            log::info!("Workspace compile commands changed, relinking all packages");
+            print_relevant(
+                self.format,
+                &link_all_compile_commands(self.workspace, self.options)?,
+            );
+        }
+
+        for package_name in pending.packages {
+            let build_package = BuildPackage(self.build_path.join(&package_name.0));
+            let source_package = self.source_package(&package_name);
+
+            if build_package
+                .compile_commands(&self.workspace.config)
+                .is_file()
+            {
+                let mut planned_link = plan_package(
+                    package_name,
+                    &build_package,
+                    source_package.as_ref(),
+                    false,
+                    self.options,
+                    &self.workspace.config,
+                );
+                if !self.options.dry_run {
+                    apply(&mut planned_link, self.options);
+                }
+                if !matches!(
+                    planned_link.action,
+                    Action::Unchanged | Action::NotApplicable(_)
+                ) {
+                    output::print(self.format, &planned_link);
+                }
+            } else if let Some(source_package) = source_package {
+                log::info!("Compile commands for {package_name} disappeared");
+                if let Some(unlinked) = unlink_package(
+                    package_name,
+                    &source_package,
+                    &self.build_path,
+                    &self.workspace.config,
+                    self.options.dry_run,
+                ) {
+                    output::print(self.format, &unlinked);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Link the compile commands of `workspace`, and keep relinking packages as their compile
+/// commands appear, change or disappear, until the watcher stops. Changes are handled once no
+/// new events arrived for `debounce`, so that a running build does not cause a flood of relinks.
+pub fn watch_compile_commands(
+    workspace: &Workspace,
+    options: &LinkOptions,
+    debounce: Duration,
+    format: Format,
+) -> Result<()> {
+    print_relevant(format, &link_all_compile_commands(workspace, options)?);
+
+    let (sender, receiver) = mpsc::channel();
+    let workspace_compile_commands = (workspace.kind == WorkspaceKind::CatkinMake).then(|| {
+        let BuildCompileCommands(path) = BuildCompileCommands::from(workspace);
+        path
+    });
+
+    let mut build_watcher = BuildWatcher {
+        workspace,
+        options,
+        format,
+        build_path: Build::from(workspace).path(),
+        workspace_compile_commands,
+        source_packages: crate::cache::source_packages(workspace, options.dry_run),
+        watcher: notify::recommended_watcher(sender)?,
+    };
+
+    if let Some(parent) = build_watcher
+        .workspace_compile_commands
+        .as_ref()
+        .and_then(|path| path.parent())
+        .map(std::path::Path::to_path_buf)
+    {
+        build_watcher.watch(&parent);
+    }
+    build_watcher.watch_build_space();
+    log::info!(
+        "Watching {} for changes",
+        build_watcher.build_path.display()
+    );
+
+    let mut pending = Pending::default();
+    loop {
+        match receiver.recv_timeout(debounce) {
+            Ok(Ok(event)) => build_watcher.handle(&event, &mut pending),
+            Ok(Err(err)) => log::warn!("Error while watching: {err}"),
+            Err(RecvTimeoutError::Timeout) => {
+                if !pending.is_empty() {
+                    build_watcher.relink(std::mem::take(&mut pending))?;
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::{config::Config, package_name::PackageName, watch::package_name};
+
+    #[test]
+    fn test_package_name() {
+        let build_path = Path::new("/ws/build");
+        let config = Config::default();
+        let foo = Some(PackageName("foo".to_string()));
+
+        assert_eq!(
+            package_name(Path::new("/ws/build/foo"), build_path, &config),
+            foo
+        );
+        assert_eq!(
+            package_name(
+                Path::new("/ws/build/foo/compile_commands.json"),
+                build_path,
+                &config
+            ),
+            foo
+        );
+        assert_eq!(
+            package_name(
+                Path::new("/ws/build/catkin_tools_prebuild"),
+                build_path,
+                &config
+            ),
+            None
+        );
+        assert_eq!(
+            package_name(
+                Path::new("/ws/build/catkin_tools_prebuild/compile_commands.json"),
+                build_path,
+                &config
+            ),
+            None
+        );
+        assert_eq!(
+            package_name(Path::new("/ws/build/foo/Makefile"), build_path, &config),
+            None
+        );
+        assert_eq!(
+            package_name(
+                Path::new("/ws/build/foo/sub/compile_commands.json"),
+                build_path,
+                &config
+            ),
+            None
+        );
+        assert_eq!(
+            package_name(Path::new("/ws/src/foo"), build_path, &config),
+            None
+        );
+    }
+}
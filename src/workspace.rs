@@ -1,7 +1,12 @@
 use core::fmt::{self, Display};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::paths::structs::Workspace;
+use crate::{
+    config::Config,
+    paths::structs::Workspace,
+    profile::{self, Profile},
+    workspace_kind::WorkspaceKind,
+};
 
 #[derive(Clone, Debug)]
 pub struct InvalidWorkspaceError;
@@ -18,6 +23,7 @@ impl Display for InvalidWorkspaceError {
 pub enum Error {
     IO(std::io::Error),
     Workspace(InvalidWorkspaceError),
+    Profile(profile::Error),
 }
 
 impl From<std::io::Error> for Error {
@@ -32,9 +38,103 @@ impl From<InvalidWorkspaceError> for Error {
     }
 }
 
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IO(err) => write!(f, "IO error: {err}"),
+            Self::Workspace(err) => write!(f, "{err}"),
+            Self::Profile(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<profile::Error> for Error {
+    fn from(value: profile::Error) -> Self {
+        Self::Profile(value)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-pub fn find_enclosing<P: AsRef<Path>>(search_start_path: P) -> Result<Workspace> {
+/// Overrides for how a workspace is interpreted, complementing what is detected on disk.
+#[derive(Clone, Debug, Default)]
+pub struct WorkspaceOptions {
+    /// The catkin profile to use instead of the active one.
+    pub profile: Option<String>,
+    /// The build space, relative to the workspace root unless absolute.
+    pub build_space: Option<PathBuf>,
+    /// The source space, relative to the workspace root unless absolute.
+    pub source_space: Option<PathBuf>,
+    /// The configuration to detect and use the workspace with.
+    pub config: Config,
+}
+
+impl WorkspaceOptions {
+    fn overrides_spaces(&self) -> bool {
+        self.build_space.is_some() && self.source_space.is_some()
+    }
+}
+
+fn load_profile(
+    path: &Path,
+    kind: WorkspaceKind,
+    profile_name: Option<&str>,
+    config: &Config,
+) -> Result<Option<Profile>> {
+    match kind {
+        WorkspaceKind::CatkinTools => Ok(Some(Profile::load(path, profile_name, config)?)),
+        WorkspaceKind::Colcon
+        | WorkspaceKind::CatkinMakeIsolated
+        | WorkspaceKind::CatkinMake
+        | WorkspaceKind::Custom => {
+            if let Some(profile_name) = profile_name {
+                log::warn!(
+                    "Ignoring profile '{profile_name}' as {kind} workspaces have no profiles"
+                );
+            }
+            Ok(None)
+        }
+    }
+}
+
+fn create(path: &Path, kind: WorkspaceKind, options: &WorkspaceOptions) -> Result<Workspace> {
+    let path = path.canonicalize()?;
+    log::info!("Found {kind} workspace path: {}", path.display());
+
+    let profile = load_profile(&path, kind, options.profile.as_deref(), &options.config)?;
+    Ok(Workspace {
+        path,
+        kind,
+        profile,
+        build_space: options.build_space.clone(),
+        source_space: options.source_space.clone(),
+        config: options.config.clone(),
+    })
+}
+
+/// Open the workspace rooted exactly at `path`, without looking at its parents.
+/// A folder that is not recognised as a workspace is accepted if both spaces are overridden.
+pub fn open<P: AsRef<Path>>(path: P, options: &WorkspaceOptions) -> Result<Workspace> {
+    let path = path.as_ref();
+
+    match WorkspaceKind::detect(path, &options.config) {
+        Some(kind) => create(path, kind, options),
+        None if options.overrides_spaces() => create(path, WorkspaceKind::Custom, options),
+        None => {
+            log::error!("Cannot find ROS workspace at {}", path.display());
+            Err(Error::Workspace(InvalidWorkspaceError {}))
+        }
+    }
+}
+
+/// Find the workspace enclosing `search_start_path`, looking up through its parents.
+/// If none is found but both spaces are overridden, `search_start_path` is used as the workspace.
+pub fn find_enclosing<P: AsRef<Path>>(
+    search_start_path: P,
+    options: &WorkspaceOptions,
+) -> Result<Workspace> {
     let mut search_next_path = search_start_path.as_ref().to_path_buf();
     log::debug!(
         "Start looking for workspace from: {}",
@@ -42,15 +142,14 @@ pub fn find_enclosing<P: AsRef<Path>>(search_start_path: P) -> Result<Workspace>
     );
 
     loop {
-        let potential_path = search_next_path.join(crate::config::METADATA_DIR_NAME);
-        log::debug!("Looking for: {}", potential_path.display());
+        log::debug!("Looking for workspace at: {}", search_next_path.display());
 
-        if potential_path.exists() && potential_path.is_dir() {
-            let search_next_path = search_next_path.canonicalize()?;
-            log::info!("Found workspace path: {}", search_next_path.display());
-            return Ok(Workspace(search_next_path));
+        if let Some(kind) = WorkspaceKind::detect(&search_next_path, &options.config) {
+            return create(&search_next_path, kind, options);
         } else if let Some(parent_path) = search_next_path.parent() {
             search_next_path = parent_path.to_path_buf();
+        } else if options.overrides_spaces() {
+            return create(search_start_path.as_ref(), WorkspaceKind::Custom, options);
         } else {
             log::error!(
                 "Cannot find ROS workspace looking up from {}",
@@ -60,3 +159,34 @@ pub fn find_enclosing<P: AsRef<Path>>(search_start_path: P) -> Result<Workspace>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::Config,
+        workspace::{find_enclosing, open, WorkspaceOptions},
+        workspace_kind::WorkspaceKind,
+    };
+
+    #[test]
+    fn test_workspaces_with_their_own_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace_path = dir.path().join("ws");
+        std::fs::create_dir_all(workspace_path.join(".my_tools")).unwrap();
+        std::fs::create_dir_all(workspace_path.join("src/foo")).unwrap();
+
+        assert!(open(&workspace_path, &WorkspaceOptions::default()).is_err());
+
+        let options = WorkspaceOptions {
+            config: Config {
+                metadata_dir_name: ".my_tools".to_string(),
+                compile_commands_name: "cc.json".to_string(),
+                ..Config::default()
+            },
+            ..WorkspaceOptions::default()
+        };
+        let workspace = find_enclosing(workspace_path.join("src/foo"), &options).unwrap();
+        assert_eq!(workspace.kind(), WorkspaceKind::CatkinTools);
+        assert_eq!(workspace.config().compile_commands_name, "cc.json");
+    }
+}
@@ -0,0 +1,229 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+use crate::{config::Config, parsers::cmake_cache::cmake_cache_parser};
+
+/// The build tool that laid out a workspace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkspaceKind {
+    /// A `catkin_tools` workspace, marked by its metadata directory.
+    CatkinTools,
+    /// A `colcon` workspace, marked by `COLCON_IGNORE` files in its build, install or log spaces.
+    Colcon,
+    /// A `catkin_make_isolated` workspace, with one build folder per package in `build_isolated`.
+    CatkinMakeIsolated,
+    /// A `catkin_make` workspace, with a single compile commands database for all packages.
+    CatkinMake,
+    /// A folder without any known layout, where the build and source spaces are given explicitly.
+    Custom,
+}
+
+fn is_catkin_tools(path: &Path, config: &Config) -> bool {
+    let metadata_path = path.join(&config.metadata_dir_name);
+    metadata_path.exists() && metadata_path.is_dir()
+}
+
+fn is_colcon(path: &Path) -> bool {
+    crate::config::COLCON_SPACE_DIRS.iter().any(|space| {
+        let marker_path = path.join(space).join(crate::config::COLCON_IGNORE_NAME);
+        marker_path.exists() && marker_path.is_file()
+    })
+}
+
+fn has_catkin_marker(path: &Path) -> bool {
+    path.join(crate::config::CATKIN_MAKE_MARKER_NAME).is_file()
+}
+
+/// The source folder the build folder at `build_path` was configured from, if catkin configured
+/// it.
+fn catkin_home_directory(build_path: &Path) -> Option<PathBuf> {
+    let cache = std::fs::read_to_string(build_path.join(crate::config::CMAKE_CACHE_NAME)).ok()?;
+    let entries = cmake_cache_parser(&cache);
+    if !entries.keys().any(|key| key.starts_with("CATKIN_")) {
+        return None;
+    }
+    let home_directory = PathBuf::from(entries.get("CMAKE_HOME_DIRECTORY")?);
+    Some(home_directory.canonicalize().unwrap_or(home_directory))
+}
+
+/// The default source space of the workspace at `path`, which catkin configured its builds from.
+fn source_space(path: &Path) -> PathBuf {
+    let source_path = path.join(crate::config::DEFAULT_SOURCE_SPACE);
+    source_path.canonicalize().unwrap_or(source_path)
+}
+
+fn is_catkin_make_isolated(path: &Path) -> bool {
+    let build_path = path.join(crate::config::ISOLATED_BUILD_SPACE);
+    build_path.is_dir()
+        && (has_catkin_marker(path)
+            || std::fs::read_dir(&build_path).is_ok_and(|entries| {
+                let source_path = source_space(path);
+                entries.filter_map(std::result::Result::ok).any(|entry| {
+                    catkin_home_directory(&entry.path())
+                        .is_some_and(|home_directory| home_directory.starts_with(&source_path))
+                })
+            }))
+}
+
+/// Whether `path` is the root of a `catkin_make` workspace, rather than e.g. a catkin package
+/// built on its own, whose build folder was configured from the package instead of the source
+/// space.
+fn is_catkin_make(path: &Path) -> bool {
+    has_catkin_marker(path)
+        || catkin_home_directory(&path.join(crate::config::DEFAULT_BUILD_SPACE))
+            .is_some_and(|home_directory| home_directory == source_space(path))
+}
+
+impl WorkspaceKind {
+    /// Determine which kind of workspace is rooted at `path`, if any.
+    pub fn detect<P: AsRef<Path>>(path: P, config: &Config) -> Option<Self> {
+        let path = path.as_ref();
+
+        if is_catkin_tools(path, config) {
+            Some(Self::CatkinTools)
+        } else if is_colcon(path) {
+            Some(Self::Colcon)
+        } else if is_catkin_make_isolated(path) {
+            Some(Self::CatkinMakeIsolated)
+        } else if is_catkin_make(path) {
+            Some(Self::CatkinMake)
+        } else {
+            None
+        }
+    }
+}
+
+impl serde::Serialize for WorkspaceKind {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Display for WorkspaceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CatkinTools => write!(f, "catkin_tools"),
+            Self::Colcon => write!(f, "colcon"),
+            Self::CatkinMakeIsolated => write!(f, "catkin_make_isolated"),
+            Self::CatkinMake => write!(f, "catkin_make"),
+            Self::Custom => write!(f, "custom"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::Config,
+        paths::{
+            path::Path,
+            structs::{Source, Workspace},
+        },
+        workspace_kind::WorkspaceKind,
+    };
+
+    #[test]
+    fn test_detect_colcon_and_catkin_tools() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let workspace = dir.path().join("colcon");
+        std::fs::create_dir_all(workspace.join("build")).unwrap();
+        assert_eq!(WorkspaceKind::detect(&workspace, &Config::default()), None);
+        std::fs::write(workspace.join("build/COLCON_IGNORE"), "").unwrap();
+        assert_eq!(
+            WorkspaceKind::detect(&workspace, &Config::default()),
+            Some(WorkspaceKind::Colcon)
+        );
+
+        let colcon = Workspace {
+            path: workspace.clone(),
+            kind: WorkspaceKind::Colcon,
+            profile: None,
+            build_space: None,
+            source_space: None,
+            config: Config::default(),
+        };
+        assert_eq!(Source::from(&colcon).path(), workspace);
+        std::fs::create_dir(workspace.join("src")).unwrap();
+        assert_eq!(Source::from(&colcon).path(), workspace.join("src"));
+
+        let workspace = dir.path().join("catkin_tools");
+        std::fs::create_dir_all(workspace.join(".catkin_tools")).unwrap();
+        std::fs::write(workspace.join(".catkin_workspace"), "").unwrap();
+        assert_eq!(
+            WorkspaceKind::detect(&workspace, &Config::default()),
+            Some(WorkspaceKind::CatkinTools)
+        );
+    }
+
+    #[test]
+    fn test_detect_catkin_make() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = dir.path().join("package");
+        std::fs::create_dir_all(package.join("build")).unwrap();
+        std::fs::write(
+            package.join("build/CMakeCache.txt"),
+            "CMAKE_BUILD_TYPE:STRING=Release\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(package.join("build_isolated/foo")).unwrap();
+        assert_eq!(WorkspaceKind::detect(&package, &Config::default()), None);
+
+        let workspace = dir.path().join("ws");
+        std::fs::create_dir_all(workspace.join("build")).unwrap();
+        std::fs::create_dir_all(workspace.join("src")).unwrap();
+        std::fs::write(
+            workspace.join("build/CMakeCache.txt"),
+            format!(
+                "CATKIN_DEVEL_PREFIX:PATH=/ws/devel\nCMAKE_HOME_DIRECTORY:INTERNAL={}\n",
+                workspace.join("src").display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            WorkspaceKind::detect(&workspace, &Config::default()),
+            Some(WorkspaceKind::CatkinMake)
+        );
+
+        let workspace = dir.path().join("isolated");
+        std::fs::create_dir_all(workspace.join("build_isolated/foo")).unwrap();
+        std::fs::create_dir_all(workspace.join("src/foo")).unwrap();
+        std::fs::write(
+            workspace.join("build_isolated/foo/CMakeCache.txt"),
+            format!(
+                "CATKIN_DEVEL_PREFIX:PATH=/ws/devel_isolated/foo\nCMAKE_HOME_DIRECTORY:INTERNAL={}\n",
+                workspace.join("src/foo").display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            WorkspaceKind::detect(&workspace, &Config::default()),
+            Some(WorkspaceKind::CatkinMakeIsolated)
+        );
+    }
+
+    #[test]
+    fn test_detect_standalone_catkin_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("ws");
+        std::fs::create_dir_all(workspace.join(".catkin_tools")).unwrap();
+
+        // A catkin package built on its own caches catkin variables too.
+        let package = workspace.join("src/foo");
+        std::fs::create_dir_all(package.join("build")).unwrap();
+        std::fs::write(
+            package.join("build/CMakeCache.txt"),
+            format!(
+                "CATKIN_DEVEL_PREFIX:PATH={0}/build/devel\nCMAKE_HOME_DIRECTORY:INTERNAL={0}\n",
+                package.display()
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(WorkspaceKind::detect(&package, &Config::default()), None);
+        assert_eq!(
+            WorkspaceKind::detect(&workspace, &Config::default()),
+            Some(WorkspaceKind::CatkinTools)
+        );
+    }
+}